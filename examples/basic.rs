@@ -20,7 +20,9 @@ fn main() -> io::Result<()> {
 
     doc.push(Paragraph::new("This is some text", font.clone()));
 
-    doc.save_to_disk("./")?;
+    for diagnostic in doc.save_to_disk("./")? {
+        println!("{diagnostic}");
+    }
 
     Ok(())
 }
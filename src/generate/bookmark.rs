@@ -0,0 +1,151 @@
+use lopdf::{Dictionary, Object, ObjectId, StringFormat};
+use printpdf::Pt;
+
+/// An entry of the document outline, placed on a page.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Bookmark {
+    pub title: String,
+    /// Nesting level, starting at 1 for top level entries
+    pub level: u8,
+    /// Top of the bookmarked content on its page
+    pub y: Pt,
+}
+
+/// A bookmark with the page it is on, starting at 1
+pub(crate) type PageBookmark = (usize, Bookmark);
+
+struct OutlineNode {
+    bookmark: PageBookmark,
+    children: Vec<usize>,
+}
+
+/// Replaces the outline of the saved PDF with a nested outline of the bookmarks. The flat outline,
+/// that was written before, is removed when the document is pruned.
+///
+/// Every bookmark becomes a child of the closest previous bookmark with a lower level.
+pub(crate) fn write_outline(
+    doc: &mut lopdf::Document,
+    bookmarks: &[PageBookmark],
+) -> lopdf::Result<()> {
+    let page_ids = doc.get_pages();
+
+    let (nodes, roots) = build_tree(bookmarks);
+    let ids = nodes
+        .iter()
+        .map(|_| doc.new_object_id())
+        .collect::<Vec<_>>();
+    let outlines_id = doc.new_object_id();
+
+    for (index, node) in nodes.iter().enumerate() {
+        let (page, bookmark) = &node.bookmark;
+        let page_id = page_ids
+            .get(&(*page as u32))
+            .copied()
+            .map_or(Object::Null, Object::Reference);
+
+        let mut dict = Dictionary::from_iter(vec![
+            ("Title", text_string(&bookmark.title)),
+            (
+                "Dest",
+                Object::Array(vec![
+                    page_id,
+                    Object::Name(b"XYZ".to_vec()),
+                    Object::Null,
+                    Object::Real(bookmark.y.0),
+                    Object::Null,
+                ]),
+            ),
+        ]);
+        set_children(&mut dict, &node.children, &ids, descendants(&nodes, index));
+        doc.set_object(ids[index], dict);
+    }
+
+    // Siblings are linked with each other and point to their parent
+    let mut set_siblings = |children: &[usize], parent: ObjectId| {
+        for (position, child) in children.iter().enumerate() {
+            let Ok(dict) = doc.get_dictionary_mut(ids[*child]) else {
+                continue;
+            };
+            dict.set("Parent", Object::Reference(parent));
+            if position > 0 {
+                dict.set("Prev", Object::Reference(ids[children[position - 1]]));
+            }
+            if let Some(next) = children.get(position + 1) {
+                dict.set("Next", Object::Reference(ids[*next]));
+            }
+        }
+    };
+    set_siblings(&roots, outlines_id);
+    for (index, node) in nodes.iter().enumerate() {
+        set_siblings(&node.children, ids[index]);
+    }
+
+    let mut outlines = Dictionary::from_iter(vec![("Type", Object::Name(b"Outlines".to_vec()))]);
+    set_children(&mut outlines, &roots, &ids, nodes.len());
+    doc.set_object(outlines_id, outlines);
+
+    let catalog = doc.catalog_mut()?;
+    catalog.set("Outlines", Object::Reference(outlines_id));
+    catalog.set("PageMode", Object::Name(b"UseOutlines".to_vec()));
+
+    Ok(())
+}
+
+/// Returns all nodes and the indices of the top level nodes
+fn build_tree(bookmarks: &[PageBookmark]) -> (Vec<OutlineNode>, Vec<usize>) {
+    let mut nodes: Vec<OutlineNode> = Vec::new();
+    let mut roots = Vec::new();
+    // Indices of the nodes that can still get children
+    let mut stack: Vec<usize> = Vec::new();
+
+    for bookmark in bookmarks {
+        while stack
+            .last()
+            .is_some_and(|parent| nodes[*parent].bookmark.1.level >= bookmark.1.level)
+        {
+            stack.pop();
+        }
+
+        let index = nodes.len();
+        match stack.last() {
+            Some(parent) => nodes[*parent].children.push(index),
+            None => roots.push(index),
+        }
+        nodes.push(OutlineNode {
+            bookmark: bookmark.clone(),
+            children: Vec::new(),
+        });
+        stack.push(index);
+    }
+
+    (nodes, roots)
+}
+
+fn descendants(nodes: &[OutlineNode], index: usize) -> usize {
+    nodes[index]
+        .children
+        .iter()
+        .map(|child| 1 + descendants(nodes, *child))
+        .sum()
+}
+
+fn set_children(dict: &mut Dictionary, children: &[usize], ids: &[ObjectId], count: usize) {
+    let (Some(first), Some(last)) = (children.first(), children.last()) else {
+        return;
+    };
+
+    dict.set("First", Object::Reference(ids[*first]));
+    dict.set("Last", Object::Reference(ids[*last]));
+    // A positive count shows the entry opened
+    dict.set("Count", Object::Integer(count as i64));
+}
+
+/// Encodes the text as UTF-16BE with byte order mark, so that every character is supported
+fn text_string(text: &str) -> Object {
+    let bytes = [0xFE, 0xFF]
+        .into_iter()
+        .chain(text.encode_utf16().flat_map(u16::to_be_bytes))
+        .collect();
+
+    Object::String(bytes, StringFormat::Hexadecimal)
+}
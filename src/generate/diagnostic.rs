@@ -0,0 +1,77 @@
+use std::fmt;
+
+use printpdf::{PdfParseErrorSeverity, PdfWarnMsg};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DiagnosticKind {
+    /// Content doesn't fit into the space it was given and is drawn outside of it or cut off
+    Overflow,
+    /// A flex item is wider than the available width and was not drawn
+    SkippedFlexItem,
+    /// The text of a table cell is wider than the cell and was clipped
+    ClippedTableCell,
+    /// A table row has too many or too few cells for the columns, or a cell spans past the last
+    /// row of its part of the table
+    TableGrid,
+    /// The page numbers of a table of contents didn't settle within the layout passes and can be
+    /// wrong
+    TableOfContents,
+    /// An SVG is drawn without its gradients, masks or embedded images
+    SimplifiedSvg,
+    /// A font could not be parsed completely, or has no glyph for a character
+    Font,
+    /// Reported by printpdf while writing the document
+    Pdf(PdfParseErrorSeverity),
+}
+
+/// A problem that didn't stop the document from being generated, but likely changed how it
+/// looks.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub message: String,
+    /// Display names of the elements from the document down to the element that caused it
+    pub path: Vec<String>,
+    /// The page it happened on, starting at 1. `None` if it doesn't belong to a page
+    pub page: Option<usize>,
+}
+
+impl Diagnostic {
+    pub fn new(kind: DiagnosticKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            path: Vec::new(),
+            page: None,
+        }
+    }
+
+    /// Adds the element, the diagnostic happened in, to the front of the path
+    pub fn in_element(mut self, display_name: &str) -> Self {
+        self.path.insert(0, display_name.to_string());
+        self
+    }
+
+    pub fn on_page(mut self, page: usize) -> Self {
+        self.page = Some(page);
+        self
+    }
+
+    /// Converts warnings of printpdf. Messages that are only informational return `None`.
+    pub(crate) fn from_pdf_warning(kind: DiagnosticKind, warning: PdfWarnMsg) -> Option<Self> {
+        (warning.severity != PdfParseErrorSeverity::Info)
+            .then(|| Self::new(kind, format!("[{:?}] {}", warning.severity, warning.msg)))
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(page) = self.page {
+            write!(f, "[page {page}] ")?;
+        }
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path.join(" > "))?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
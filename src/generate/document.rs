@@ -1,19 +1,28 @@
 use std::{
+    collections::{BTreeMap, HashMap},
     io::{self, Cursor},
     path::Path,
+    sync::Arc,
 };
 
-use image::GenericImageView;
+use image::{GenericImageView, imageops::FilterType};
 use printpdf::{
-    ImageCompression, ImageOptimizationOptions, Mm, Op, ParsedFont, PdfDocument, PdfPage,
-    PdfSaveOptions, PdfWarnMsg, Point, Pt, Px, RawImage, RawImageData, RawImageFormat, XObjectId,
+    FontId, ImageCompression, ImageOptimizationOptions, Mm, Op, ParsedFont, PdfDocument, PdfPage,
+    PdfParseErrorSeverity, PdfSaveOptions, Point, Pt, Px, RawImage, Rect, XObjectId,
     XObjectTransform,
 };
+use svg2pdf::usvg::fontdb;
 
 use crate::generate::{
+    bookmark::{Bookmark, PageBookmark, write_outline},
+    diagnostic::{Diagnostic, DiagnosticKind},
     element::{Element, element_builder::ElementBuilder, image::Image},
-    font::Font,
+    error::{BuildError, BuildErrorKind},
+    font::{Font, FontFamily, FontStyle},
+    image_data::{ImageInfo, ImageLoadOptions, JpegInfo, raw_image},
+    link::goto_annotation,
     padding::Padding,
+    svg::{SvgImage, add_svg_font, embed_svgs},
 };
 
 #[derive(Clone, Debug)]
@@ -33,24 +42,78 @@ impl DocumentStyle {
     }
 }
 
+/// The position of a page in the generated document.
+#[derive(Clone, Copy, Debug)]
+pub struct PageInfo {
+    /// The page number, starting at 1
+    pub page: usize,
+    /// The total number of pages in the document
+    pub total: usize,
+}
+
+/// An element that is rendered on every page, like a header or footer.
+struct PageDecoration {
+    element: Box<dyn Element>,
+    /// Distance between the element and the edge of the page
+    margin: Mm,
+}
+
+/// The pages of a single layout pass
+struct LayoutPass {
+    pages: Vec<Page>,
+    diagnostics: Vec<Diagnostic>,
+    /// Whether a table of contents was built, which needs another pass when its entries changed
+    toc_used: bool,
+}
+
 struct DocumentImage {
     xobject_id: XObjectId,
     position: Point,
 }
 
-#[derive(Default)]
+/// A generated page with its own page setup.
+#[derive(Clone, Debug)]
 pub struct Page {
-    ops: Vec<Op>,
+    pub ops: Vec<Op>,
+    pub style: DocumentStyle,
+    /// Named positions on this page, that links can jump to
+    pub(crate) anchors: Vec<(String, Pt)>,
+    /// Links to anchors, which are resolved when all pages are generated
+    pub(crate) anchor_links: Vec<(Rect, String)>,
+    /// Entries of the document outline on this page
+    pub(crate) bookmarks: Vec<Bookmark>,
 }
 
 impl Page {
+    pub fn new(style: DocumentStyle) -> Self {
+        Self {
+            ops: Vec::new(),
+            style,
+            anchors: Vec::new(),
+            anchor_links: Vec::new(),
+            bookmarks: Vec::new(),
+        }
+    }
+
+    /// Adds the content of another page on top of this page
+    pub(crate) fn append(&mut self, other: Page) {
+        self.ops.extend(other.ops);
+        self.anchors.extend(other.anchors);
+        self.anchor_links.extend(other.anchor_links);
+        self.bookmarks.extend(other.bookmarks);
+    }
+
     pub fn push(&mut self, op: Op) {
         self.ops.push(op);
     }
 
-    pub fn extend(&mut self, iter: impl Iterator<Item = Op>) {
+    pub fn extend(&mut self, iter: impl IntoIterator<Item = Op>) {
         self.ops.extend(iter);
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
 }
 
 pub struct Document {
@@ -61,13 +124,33 @@ pub struct Document {
 
     footer_img: Option<DocumentImage>,
     header_img: Option<(DocumentImage, Mm)>,
+    /// Replaced with their complete drawings when the document is saved
+    svgs: Vec<SvgImage>,
+    svg_fonts: Option<Arc<fontdb::Database>>,
+    /// Images loaded from files by the hash of their data and load options
+    loaded_images: HashMap<u64, XObjectId>,
+    image_info: BTreeMap<XObjectId, ImageInfo>,
+
+    header: Option<PageDecoration>,
+    footer: Option<PageDecoration>,
 
     default_font: Option<Font>,
+    font_families: HashMap<String, FontFamily>,
 
     default_font_size: Pt,
     default_font_height_offset: Pt,
+
+    /// Bookmarks of the previous layout pass, used by tables of contents
+    toc_entries: Vec<PageBookmark>,
+
+    /// Reported before the document is generated, e.g. while loading fonts
+    diagnostics: Vec<Diagnostic>,
 }
 
+/// The layout is repeated until the page numbers of the table of contents don't change anymore,
+/// but at most this often.
+const MAX_LAYOUT_PASSES: usize = 4;
+
 impl Document {
     pub fn new(
         name: &str,
@@ -87,9 +170,18 @@ impl Document {
             },
             footer_img: None,
             header_img: None,
+            svgs: Vec::new(),
+            svg_fonts: None,
+            loaded_images: HashMap::new(),
+            image_info: BTreeMap::new(),
+            header: None,
+            footer: None,
             default_font: None,
+            font_families: HashMap::new(),
             default_font_size,
             default_font_height_offset,
+            toc_entries: Vec::new(),
+            diagnostics: Vec::new(),
         }
     }
 
@@ -101,6 +193,12 @@ impl Document {
         &self.pdf_document
     }
 
+    /// The bookmarks of the document with their page numbers, known from the previous layout
+    /// pass
+    pub(crate) fn toc_entries(&self) -> &[PageBookmark] {
+        &self.toc_entries
+    }
+
     pub fn push<E>(&mut self, element: E)
     where
         E: Element + 'static,
@@ -116,6 +214,69 @@ impl Document {
     ///
     /// If this is the first font added, it will be set as the default font
     pub fn add_font(&mut self, font_data: &[u8]) -> io::Result<Font> {
+        let font_id = self.load_font(font_data)?;
+        let font = Font::new(
+            font_id,
+            self.default_font_size,
+            self.default_font_height_offset,
+        );
+
+        if self.default_font.is_none() {
+            self.default_font = Some(font.clone());
+        }
+
+        Ok(font)
+    }
+
+    /// Loads a font and registers it in the family `family` with the given weight and style.
+    ///
+    /// Other variants of the family can then be derived from the returned font, e.g. with
+    /// [`Font::bold`] or [`Font::italic`]. Variable fonts are embedded as they are, so every
+    /// weight should be registered from its own static font file.
+    ///
+    /// If this is the first font added, it will be set as the default font
+    pub fn add_font_to_family(
+        &mut self,
+        family: &str,
+        weight: u16,
+        style: FontStyle,
+        font_data: &[u8],
+    ) -> io::Result<Font> {
+        let font_id = self.load_font(font_data)?;
+        let font_family = self.font_families.entry(family.to_string()).or_default();
+        font_family.add_variant(font_id.clone(), weight, style);
+
+        let font = Font::new(
+            font_id,
+            self.default_font_size,
+            self.default_font_height_offset,
+        )
+        .with_family(font_family.clone(), weight, style);
+
+        if self.default_font.is_none() {
+            self.default_font = Some(font.clone());
+        }
+
+        Ok(font)
+    }
+
+    /// The regular variant of a registered font family
+    pub fn font_family(&self, family: &str) -> Option<Font> {
+        let font_family = self.font_families.get(family)?;
+        let font_id = font_family.font_ids().into_iter().next()?;
+
+        Some(
+            Font::new(
+                font_id,
+                self.default_font_size,
+                self.default_font_height_offset,
+            )
+            .with_family(font_family.clone(), 0, FontStyle::Normal)
+            .regular(),
+        )
+    }
+
+    fn load_font(&mut self, font_data: &[u8]) -> io::Result<FontId> {
         let mut warnings = Vec::new();
 
         let Some(parsed_font) = ParsedFont::from_bytes(font_data, 0, &mut warnings) else {
@@ -128,18 +289,23 @@ impl Document {
             return Err(io::Error::new(io::ErrorKind::InvalidInput, message));
         };
 
-        let font_id = self.pdf_document.add_font(&parsed_font);
-        let font = Font::new(
-            font_id,
-            self.default_font_size,
-            self.default_font_height_offset,
-        );
+        // Fonts often have many problems of the same kind, so they are reported together
+        let mut problems = warnings
+            .into_iter()
+            .filter_map(|warn| Diagnostic::from_pdf_warning(DiagnosticKind::Font, warn));
+        if let Some(mut first) = problems.next() {
+            let more = problems.count();
+            if more > 0 {
+                first.message += &format!(" (and {more} more problems)");
+            }
+            self.diagnostics.push(first);
+        }
 
-        if self.default_font.is_none() {
-            self.default_font = Some(font.clone());
+        if let Some(svg_fonts) = &mut self.svg_fonts {
+            add_svg_font(Arc::make_mut(svg_fonts), font_data.to_vec());
         }
 
-        Ok(font)
+        Ok(self.pdf_document.add_font(&parsed_font))
     }
 
     pub fn get_default_font(&self) -> Font {
@@ -148,8 +314,12 @@ impl Document {
             .expect("Default font not set. Please add a font using `add_font` method.")
     }
 
-    pub fn set_header_image(&mut self, image_data: &[u8], after_image_padding: Mm) {
-        let raw_image = RawImage::decode_from_bytes(image_data, &mut Vec::new()).unwrap();
+    pub fn set_header_image(
+        &mut self,
+        image_data: &[u8],
+        after_image_padding: Mm,
+    ) -> Result<(), BuildError> {
+        let raw_image = decode_image(image_data)?;
 
         let height_pt = Px(raw_image.height).into_pt(300.0);
 
@@ -165,10 +335,30 @@ impl Document {
             },
             after_image_padding,
         ));
+
+        Ok(())
     }
 
-    pub fn load_image(&mut self, image_data: &[u8]) -> Result<XObjectId, String> {
-        let loaded_image = image::load_from_memory(image_data).map_err(|e| format!("{e}"))?;
+    pub fn load_image(&mut self, image_data: &[u8]) -> Result<XObjectId, BuildError> {
+        self.load_image_with(image_data, &ImageLoadOptions::default())
+    }
+
+    /// Loads an image, that can be downsampled and embedded without decoding it.
+    ///
+    /// Images with the same data and options are only embedded once, loading them again returns
+    /// the same id.
+    ///
+    /// The alpha channel of transparent images is kept as a soft mask.
+    pub fn load_image_with(
+        &mut self,
+        image_data: &[u8],
+        options: &ImageLoadOptions,
+    ) -> Result<XObjectId, BuildError> {
+        let hash = options.content_hash(image_data);
+        if let Some(xobject_id) = self.loaded_images.get(&hash) {
+            return Ok(xobject_id.clone());
+        }
+
         let mut cursor = Cursor::new(image_data);
         let orientation = exif::Reader::new()
             .read_from_container(&mut cursor)
@@ -178,6 +368,28 @@ impl Document {
                     .and_then(|f| f.value.get_uint(0))
             })
             .unwrap_or(1);
+        let max_width = options.max_pixel_width(self.style.inner_width());
+        let dpi = native_dpi(image_data);
+
+        let jpeg = JpegInfo::read(image_data).filter(|jpeg| {
+            options.jpeg_passthrough()
+                && orientation == 1
+                && max_width.is_none_or(|max_width| jpeg.width <= max_width as usize)
+        });
+        if let Some(jpeg) = jpeg {
+            let xobject_id = self.pdf_document.add_xobject(&jpeg.xobject(image_data));
+            let info = ImageInfo {
+                width: Px(jpeg.width),
+                height: Px(jpeg.height),
+                dpi,
+            };
+            self.image_info.insert(xobject_id.clone(), info);
+            self.loaded_images.insert(hash, xobject_id.clone());
+            return Ok(xobject_id);
+        }
+
+        let loaded_image = image::load_from_memory(image_data)
+            .map_err(|err| BuildErrorKind::ImageDecode(err.to_string()))?;
         let orientated_image = match orientation {
             2 => loaded_image.fliph(),
             3 => loaded_image.rotate180(),
@@ -189,25 +401,101 @@ impl Document {
             _ => loaded_image,
         };
 
-        let (width, height) = orientated_image.dimensions();
-        let rgba = orientated_image.into_rgb8().into_raw();
-        let raw_image = RawImage {
-            pixels: RawImageData::U8(rgba),
-            width: width as usize,
-            height: height as usize,
-            data_format: RawImageFormat::RGB8,
-            tag: vec![],
+        let (original_width, original_height) = orientated_image.dimensions();
+        let downsampled_image = match max_width {
+            // Keeps the aspect ratio
+            Some(max_width) if original_width > max_width => {
+                orientated_image.resize(max_width, u32::MAX, FilterType::Lanczos3)
+            }
+            _ => orientated_image,
+        };
+
+        let xobject_id = self.add_image(raw_image(downsampled_image));
+        let info = ImageInfo {
+            width: Px(original_width as usize),
+            height: Px(original_height as usize),
+            dpi,
         };
+        self.image_info.insert(xobject_id.clone(), info);
+        self.loaded_images.insert(hash, xobject_id.clone());
 
-        Ok(self.add_image(raw_image))
+        Ok(xobject_id)
+    }
+
+    /// The size and resolution of an image file loaded with [`Document::load_image`]
+    pub(crate) fn image_info(&self, image: &XObjectId) -> Option<ImageInfo> {
+        self.image_info.get(image).copied()
     }
 
     pub fn add_image(&mut self, image: RawImage) -> XObjectId {
         self.pdf_document.add_image(&image)
     }
 
-    pub fn set_footer_image(&mut self, image_data: &[u8]) {
-        let raw_image = RawImage::decode_from_bytes(image_data, &mut Vec::new()).unwrap();
+    /// Adds an SVG as vector graphics, that can be used like an image in
+    /// [`Image`](crate::generate::element::image::Image) and
+    /// [`ImageFlex`](crate::generate::element::image_flex::ImageFlex).
+    ///
+    /// Its size is taken from the SVG at the 300 DPI of raster images. Text is converted to paths
+    /// with the fonts of the document and the fonts added with [`Document::add_svg_font`]. Text
+    /// without a font family uses the default font.
+    ///
+    /// Gradients, masks and embedded images are only kept by [`Document::save`],
+    /// [`Document::generate_document`] draws a simplified version and reports it.
+    pub fn add_svg(&mut self, svg: &str) -> Result<XObjectId, BuildError> {
+        let fonts = self.svg_fonts().clone();
+        let svg = SvgImage::add(&mut self.pdf_document, svg, fonts)
+            .map_err(BuildErrorKind::ImageDecode)?;
+
+        let xobject_id = svg.xobject_id.clone();
+        self.svgs.push(svg);
+        Ok(xobject_id)
+    }
+
+    /// Adds a font, that is only used for the text of SVGs. The fonts of the document don't have
+    /// to be added again.
+    pub fn add_svg_font(&mut self, font_data: &[u8]) -> io::Result<()> {
+        if add_svg_font(Arc::make_mut(self.svg_fonts()), font_data.to_vec()) {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no fonts found in the font data",
+            ))
+        }
+    }
+
+    /// Also uses the fonts installed on the system for the text of SVGs. Finding them takes a
+    /// while and SVGs can look different on other systems, so only the fonts of the document are
+    /// used by default.
+    pub fn load_system_fonts_for_svgs(&mut self) {
+        Arc::make_mut(self.svg_fonts()).load_system_fonts();
+    }
+
+    /// The fonts for the text of SVGs, created from the fonts of the document when they are
+    /// needed first
+    fn svg_fonts(&mut self) -> &mut Arc<fontdb::Database> {
+        self.svg_fonts.get_or_insert_with(|| {
+            let default_font_id = self.default_font.as_ref().map(Font::font_id);
+            let mut fonts = self
+                .pdf_document
+                .resources
+                .fonts
+                .map
+                .iter()
+                .collect::<Vec<_>>();
+            // The first font is used for text without a font family
+            fonts.sort_by_key(|(font_id, _)| Some(*font_id) != default_font_id.as_ref());
+
+            let mut database = fontdb::Database::new();
+            for (_, font) in fonts {
+                add_svg_font(&mut database, font.original_bytes.clone());
+            }
+            Arc::new(database)
+        })
+    }
+
+    pub fn set_footer_image(&mut self, image_data: &[u8]) -> Result<(), BuildError> {
+        let raw_image = decode_image(image_data)?;
 
         let header = self.pdf_document.add_image(&raw_image);
 
@@ -218,12 +506,46 @@ impl Document {
                 y: Pt(0.0),
             },
         });
+
+        Ok(())
+    }
+
+    /// Sets an element that is rendered at the top of every page.
+    ///
+    /// The element is placed `margin` below the top edge of the page, so it should fit into the
+    /// top padding of the document. Texts can contain the placeholders
+    /// [`PAGE_NUMBER_PLACEHOLDER`](crate::generate::text_gen::PAGE_NUMBER_PLACEHOLDER) and
+    /// [`PAGE_COUNT_PLACEHOLDER`](crate::generate::text_gen::PAGE_COUNT_PLACEHOLDER), which are
+    /// resolved after all pages have been generated.
+    pub fn set_header<E>(&mut self, element: E, margin: Mm)
+    where
+        E: Element + 'static,
+    {
+        self.header = Some(PageDecoration {
+            element: Box::new(element),
+            margin,
+        });
+    }
+
+    /// Sets an element that is rendered at the bottom of every page.
+    ///
+    /// The bottom of the element is placed `margin` above the bottom edge of the page. The same
+    /// placeholders as in [`Document::set_header`] are supported.
+    pub fn set_footer<E>(&mut self, element: E, margin: Mm)
+    where
+        E: Element + 'static,
+    {
+        self.footer = Some(PageDecoration {
+            element: Box::new(element),
+            margin,
+        });
     }
 
     /// Saves the document to disk at the specified path.
     ///
     /// If the path is a directory, the document will be saved with its title as the filename.
-    pub fn save_to_disk(self, path: impl AsRef<Path>) -> io::Result<()> {
+    /// Returns the diagnostics of [`Document::save`].
+    pub fn save_to_disk(self, path: impl AsRef<Path>) -> io::Result<Vec<Diagnostic>> {
         let path = path.as_ref();
         let output_path = if path.is_dir() {
             path.join(Path::new(&format!(
@@ -234,17 +556,19 @@ impl Document {
             path.to_path_buf()
         };
 
-        let (data, warnings) = self.save();
+        let (data, diagnostics) = self.save().map_err(io::Error::other)?;
 
-        for warn in warnings {
-            println!("[{:?}] {}", warn.severity, warn.msg);
-        }
-
-        std::fs::write(output_path, data)
+        std::fs::write(output_path, data)?;
+        Ok(diagnostics)
     }
 
-    pub fn save(self) -> (Vec<u8>, Vec<PdfWarnMsg>) {
-        let generated = self.generate_document();
+    /// Generates and serializes the document.
+    ///
+    /// Fails with the path of the element, that could not be built. Problems that didn't stop the
+    /// generation are returned as diagnostics.
+    pub fn save(mut self) -> Result<(Vec<u8>, Vec<Diagnostic>), BuildError> {
+        let svgs = std::mem::take(&mut self.svgs);
+        let (generated, bookmarks, mut diagnostics) = self.generate()?;
         let mut warn_messages = Vec::new();
         let bytes = generated.save(
             &PdfSaveOptions {
@@ -259,16 +583,134 @@ impl Document {
             &mut warn_messages,
         );
 
-        (bytes, warn_messages)
+        diagnostics.extend(warn_messages.into_iter().filter_map(|warn| {
+            Diagnostic::from_pdf_warning(DiagnosticKind::Pdf(warn.severity), warn)
+        }));
+
+        let bytes = post_process(bytes, &bookmarks, &svgs, &mut diagnostics);
+
+        Ok((bytes, diagnostics))
+    }
+
+    /// Generates all pages of the document.
+    ///
+    /// The bookmarks of headings are only added as a flat outline without positions, because
+    /// printpdf can't write nested bookmarks. The nested outline is added by [`Document::save`],
+    /// after the document was serialized.
+    ///
+    /// SVGs are drawn without gradients, masks and embedded images, which is reported for every
+    /// SVG that has them. Only [`Document::save`] can embed them.
+    pub fn generate_document(self) -> Result<(PdfDocument, Vec<Diagnostic>), BuildError> {
+        let simplified_svgs = self.svgs.iter().filter(|svg| svg.simplified).count();
+        let (pdf_document, _, mut diagnostics) = self.generate()?;
+        if simplified_svgs > 0 {
+            diagnostics.push(Diagnostic::new(
+                DiagnosticKind::SimplifiedSvg,
+                format!(
+                    "{} drawn without gradients, masks or embedded images, use Document::save to \
+                     keep them",
+                    match simplified_svgs {
+                        1 => "1 SVG is".to_string(),
+                        count => format!("{count} SVGs are"),
+                    }
+                ),
+            ));
+        }
+        Ok((pdf_document, diagnostics))
+    }
+
+    /// Returns the generated document, its bookmarks in document order and the diagnostics
+    fn generate(mut self) -> Result<(PdfDocument, Vec<PageBookmark>, Vec<Diagnostic>), BuildError> {
+        let mut layout = self.layout_pages()?;
+        // Tables of contents need the final page numbers, which are only known after the layout.
+        // Their own length can move the headings again, so the layout is repeated.
+        for _ in 1..MAX_LAYOUT_PASSES {
+            if !layout.toc_used {
+                break;
+            }
+
+            let bookmarks = Self::collect_bookmarks(&layout.pages);
+            if bookmarks == self.toc_entries {
+                break;
+            }
+
+            self.toc_entries = bookmarks;
+            layout = self.layout_pages()?;
+        }
+
+        let LayoutPass {
+            mut pages,
+            diagnostics: layout_diagnostics,
+            toc_used,
+        } = layout;
+        let mut diagnostics = std::mem::take(&mut self.diagnostics);
+        diagnostics.extend(layout_diagnostics);
+        if toc_used && Self::collect_bookmarks(&pages) != self.toc_entries {
+            diagnostics.push(Diagnostic::new(
+                DiagnosticKind::TableOfContents,
+                format!(
+                    "The table of contents still changed after {MAX_LAYOUT_PASSES} layout passes, \
+                     its page numbers can be wrong"
+                ),
+            ));
+        }
+
+        let footer_ops = self.generate_footer_ops();
+        let total = pages.len();
+        for (index, page) in pages.iter_mut().enumerate() {
+            page.extend(footer_ops.iter().cloned());
+            diagnostics.extend(self.append_page_decorations(
+                page,
+                PageInfo {
+                    page: index + 1,
+                    total,
+                },
+            )?);
+        }
+
+        let anchors = pages
+            .iter()
+            .enumerate()
+            .flat_map(|(index, page)| {
+                page.anchors
+                    .iter()
+                    .map(move |(name, y)| (name.as_str(), (index + 1, *y)))
+            })
+            .collect::<HashMap<_, _>>();
+        let link_ops = pages
+            .iter()
+            .map(|page| Self::resolve_anchor_links(page, &anchors))
+            .collect::<Vec<_>>();
+
+        let bookmarks = Self::collect_bookmarks(&pages);
+        for (page, bookmark) in &bookmarks {
+            self.pdf_document.add_bookmark(&bookmark.title, *page);
+        }
+
+        let pages = pages
+            .into_iter()
+            .zip(link_ops)
+            .map(|(mut page, link_ops)| {
+                page.extend(link_ops);
+                PdfPage::new(page.style.width, page.style.height, page.ops)
+            })
+            .collect();
+
+        self.pdf_document.with_pages(pages);
+
+        Ok((self.pdf_document, bookmarks, diagnostics))
     }
 
-    pub fn generate_document(mut self) -> PdfDocument {
-        let mut current_builder = ElementBuilder::new(&self);
+    /// Builds all elements onto pages, without headers and footers
+    fn layout_pages(&self) -> Result<LayoutPass, BuildError> {
+        let mut current_builder = ElementBuilder::new(self);
         // Insert header image
         if let Some((header_image, after_image_padding)) = &self.header_img {
             let img = Image::new(header_image.xobject_id.clone(), Some(self.style.width));
 
-            let (_, img_height) = current_builder.measure_image(&img);
+            let (_, img_height) = current_builder
+                .measure_image(&img)
+                .map_err(|e| e.in_element(img.display_name()))?;
 
             current_builder.advance_cursor(
                 img_height - self.style.padding.top.into_pt() + after_image_padding.into_pt(),
@@ -279,24 +721,30 @@ impl Document {
                 .expect("We have at least one page")
                 .extend(self.generate_header_ops());
         }
-        let footer_ops = self.generate_footer_ops();
 
         for element in &self.elements {
-            element.build(&mut current_builder);
+            current_builder.build_child(element.as_ref())?;
         }
 
-        let pages = current_builder
-            .pages
-            .into_iter()
-            .map(|mut page| {
-                page.extend_from_slice(&footer_ops);
-                PdfPage::new(self.style.width, self.style.height, page)
-            })
-            .collect();
-
-        self.pdf_document.with_pages(pages);
+        let toc_used = current_builder.toc_used();
+        let (pages, diagnostics) = current_builder.finish();
+        Ok(LayoutPass {
+            pages,
+            diagnostics,
+            toc_used,
+        })
+    }
 
-        self.pdf_document
+    fn collect_bookmarks(pages: &[Page]) -> Vec<PageBookmark> {
+        pages
+            .iter()
+            .enumerate()
+            .flat_map(|(index, page)| {
+                page.bookmarks
+                    .iter()
+                    .map(move |bookmark| (index + 1, bookmark.clone()))
+            })
+            .collect()
     }
     // pub fn generate_document(mut self) -> PdfDocument {
     //     let start_origin = printpdf::Point::new(
@@ -354,6 +802,92 @@ impl Document {
     //     self.pdf_document
     // }
 
+    /// Creates the link annotations for links to anchors. Links to unknown anchors are skipped.
+    fn resolve_anchor_links(page: &Page, anchors: &HashMap<&str, (usize, Pt)>) -> Vec<Op> {
+        page.anchor_links
+            .iter()
+            .filter_map(|(rect, name)| {
+                let (page, y) = anchors.get(name.as_str())?;
+                Some(goto_annotation(rect.clone(), *page, *y))
+            })
+            .collect()
+    }
+
+    /// Builds the header and footer elements for a single page and returns their diagnostics.
+    fn append_page_decorations(
+        &self,
+        page: &mut Page,
+        page_info: PageInfo,
+    ) -> Result<Vec<Diagnostic>, BuildError> {
+        let mut diagnostics = Vec::new();
+        let style = &page.style.clone();
+        let x = style.padding.left.into_pt();
+
+        if let Some(header) = &self.header {
+            let origin = Point {
+                x,
+                y: (style.height - header.margin).into_pt(),
+            };
+            let (header_page, header_diagnostics) =
+                self.build_page_decoration(header, style, origin, page_info)?;
+            page.append(header_page);
+            diagnostics.extend(header_diagnostics);
+        }
+
+        if let Some(footer) = &self.footer {
+            let measure_origin = Point {
+                x,
+                y: style.height.into_pt(),
+            };
+            let measure_builder =
+                ElementBuilder::new_page_decoration(self, style, measure_origin, page_info);
+            let height = measure_builder.measure_child_height(footer.element.as_ref())?;
+
+            let origin = Point {
+                x,
+                y: footer.margin.into_pt() + height,
+            };
+            let (footer_page, footer_diagnostics) =
+                self.build_page_decoration(footer, style, origin, page_info)?;
+            page.append(footer_page);
+            diagnostics.extend(footer_diagnostics);
+        }
+
+        Ok(diagnostics)
+    }
+
+    fn build_page_decoration(
+        &self,
+        decoration: &PageDecoration,
+        style: &DocumentStyle,
+        origin: Point,
+        page_info: PageInfo,
+    ) -> Result<(Page, Vec<Diagnostic>), BuildError> {
+        let mut builder = ElementBuilder::new_page_decoration(self, style, origin, page_info);
+        builder.build_child(decoration.element.as_ref())?;
+
+        let (pages, mut diagnostics) = builder.finish();
+        if pages.len() > 1 {
+            diagnostics.push(
+                Diagnostic::new(
+                    DiagnosticKind::Overflow,
+                    "Doesn't fit on the page, the rest is cut off",
+                )
+                .in_element(decoration.element.display_name()),
+            );
+        }
+        for diagnostic in diagnostics.iter_mut() {
+            diagnostic.page = Some(page_info.page);
+        }
+
+        // Headers and footers can not break onto another page
+        let page = pages
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| Page::new(style.clone()));
+        Ok((page, diagnostics))
+    }
+
     fn generate_header_ops(&self) -> Vec<Op> {
         if let Some((header, _)) = &self.header_img {
             vec![Op::UseXobject {
@@ -384,3 +918,129 @@ impl Document {
         }
     }
 }
+
+/// Writes what printpdf can't write into the serialized document: the complete drawings of the
+/// SVGs and the nested outline. The document is parsed and written again only once for all of
+/// them. Returns the bytes unchanged, if there is nothing to do or the document can't be parsed.
+fn post_process(
+    bytes: Vec<u8>,
+    bookmarks: &[PageBookmark],
+    svgs: &[SvgImage],
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Vec<u8> {
+    if bookmarks.is_empty() && svgs.is_empty() {
+        return bytes;
+    }
+
+    let mut warn = |message: String| {
+        diagnostics.push(Diagnostic::new(
+            DiagnosticKind::Pdf(PdfParseErrorSeverity::Warning),
+            message,
+        ));
+    };
+
+    let mut doc = match lopdf::Document::load_mem(&bytes) {
+        Ok(doc) => doc,
+        Err(err) => {
+            warn(format!("Failed to post-process the document: {err}"));
+            return bytes;
+        }
+    };
+
+    // printpdf drops the resources of SVGs
+    if let Err(err) = embed_svgs(&mut doc, svgs) {
+        warn(format!("Failed to embed the SVGs: {err}"));
+    }
+    // printpdf can only write a flat outline without positions
+    if !bookmarks.is_empty()
+        && let Err(err) = write_outline(&mut doc, bookmarks)
+    {
+        warn(format!("Failed to write the document outline: {err}"));
+    }
+
+    // Removes the flat outline and the replaced placeholders
+    doc.prune_objects();
+
+    let mut processed = Vec::new();
+    match doc.save_to(&mut processed) {
+        Ok(()) => processed,
+        Err(err) => {
+            warn(format!("Failed to post-process the document: {err}"));
+            bytes
+        }
+    }
+}
+
+fn decode_image(image_data: &[u8]) -> Result<RawImage, BuildError> {
+    let image = image::load_from_memory(image_data)
+        .map_err(|err| BuildErrorKind::ImageDecode(err.to_string()))?;
+    Ok(raw_image(image))
+}
+
+/// The resolution stored in the metadata of an image file
+fn native_dpi(image_data: &[u8]) -> Option<f32> {
+    exif_dpi(image_data)
+        .or_else(|| png_dpi(image_data))
+        .or_else(|| jfif_dpi(image_data))
+        .filter(|dpi| dpi.is_finite() && *dpi > 0.0)
+}
+
+fn exif_dpi(image_data: &[u8]) -> Option<f32> {
+    let exif = exif::Reader::new()
+        .read_from_container(&mut Cursor::new(image_data))
+        .ok()?;
+    let resolution = match &exif
+        .get_field(exif::Tag::XResolution, exif::In::PRIMARY)?
+        .value
+    {
+        exif::Value::Rational(values) => values.first()?.to_f32(),
+        _ => return None,
+    };
+    let unit = exif
+        .get_field(exif::Tag::ResolutionUnit, exif::In::PRIMARY)
+        .and_then(|field| field.value.get_uint(0))
+        .unwrap_or(2);
+
+    match unit {
+        2 => Some(resolution),
+        3 => Some(resolution * 2.54),
+        // Only the aspect ratio is known
+        _ => None,
+    }
+}
+
+/// Reads the pHYs chunk, which stores pixels per meter
+fn png_dpi(image_data: &[u8]) -> Option<f32> {
+    let mut chunks = image_data.strip_prefix(b"\x89PNG\r\n\x1a\n")?;
+    while chunks.len() >= 8 {
+        let length = u32::from_be_bytes(chunks[0..4].try_into().ok()?) as usize;
+        let (kind, data) = (&chunks[4..8], chunks.get(8..8 + length)?);
+        match kind {
+            b"pHYs" if data.len() >= 9 && data[8] == 1 => {
+                let pixels_per_meter = u32::from_be_bytes(data[0..4].try_into().ok()?);
+                return Some(pixels_per_meter as f32 * 0.0254);
+            }
+            // pHYs has to come before the image data
+            b"IDAT" => return None,
+            _ => chunks = chunks.get(12 + length..)?,
+        }
+    }
+
+    None
+}
+
+/// Reads the density of the JFIF header of JPEG files
+fn jfif_dpi(image_data: &[u8]) -> Option<f32> {
+    let segment = image_data.strip_prefix(&[0xFF, 0xD8, 0xFF, 0xE0])?;
+    let header = segment.get(2..14)?;
+    if &header[0..5] != b"JFIF\0" {
+        return None;
+    }
+
+    let density = u16::from_be_bytes([header[8], header[9]]) as f32;
+    match header[7] {
+        1 => Some(density),
+        2 => Some(density * 2.54),
+        _ => None,
+    }
+}
@@ -2,10 +2,46 @@ use printpdf::{Mm, Pt};
 
 use crate::generate::{document::Document, padding::Padding};
 
+/// Paper format of the document.
+///
+/// All sizes are given in portrait orientation.
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum DocumentFormat {
+    A0,
+    A1,
+    A2,
+    A3,
     A4,
+    A5,
+    A6,
+    B0,
+    B1,
+    B2,
+    B3,
+    B4,
+    B5,
+    B6,
+    /// US Letter, 8.5 x 11 in
+    Letter,
+    /// US Legal, 8.5 x 14 in
+    Legal,
+    /// US Tabloid, 11 x 17 in
+    Tabloid,
+    /// DL envelope, 110 x 220 mm
+    EnvelopeDL,
+    /// C4 envelope, fits an unfolded A4 sheet
+    EnvelopeC4,
+    /// C5 envelope, fits an A4 sheet folded once
+    EnvelopeC5,
+    /// C6 envelope, fits an A4 sheet folded twice
+    EnvelopeC6,
+    /// US No. 10 envelope, 4.125 x 9.5 in
+    Envelope10,
+    /// Custom width and height
+    Custom(Mm, Mm),
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum DocumentOrientation {
     Portrait,
     Landscape,
@@ -71,15 +107,39 @@ impl DocumentBuilder {
 }
 
 impl DocumentFormat {
-    fn dimensions(&self) -> (Mm, Mm) {
+    /// Width and height of the format in portrait orientation
+    pub fn dimensions(&self) -> (Mm, Mm) {
         match self {
+            DocumentFormat::A0 => (Mm(841.0), Mm(1189.0)),
+            DocumentFormat::A1 => (Mm(594.0), Mm(841.0)),
+            DocumentFormat::A2 => (Mm(420.0), Mm(594.0)),
+            DocumentFormat::A3 => (Mm(297.0), Mm(420.0)),
             DocumentFormat::A4 => (Mm(210.0), Mm(297.0)),
+            DocumentFormat::A5 => (Mm(148.0), Mm(210.0)),
+            DocumentFormat::A6 => (Mm(105.0), Mm(148.0)),
+            DocumentFormat::B0 => (Mm(1000.0), Mm(1414.0)),
+            DocumentFormat::B1 => (Mm(707.0), Mm(1000.0)),
+            DocumentFormat::B2 => (Mm(500.0), Mm(707.0)),
+            DocumentFormat::B3 => (Mm(353.0), Mm(500.0)),
+            DocumentFormat::B4 => (Mm(250.0), Mm(353.0)),
+            DocumentFormat::B5 => (Mm(176.0), Mm(250.0)),
+            DocumentFormat::B6 => (Mm(125.0), Mm(176.0)),
+            DocumentFormat::Letter => (Mm(215.9), Mm(279.4)),
+            DocumentFormat::Legal => (Mm(215.9), Mm(355.6)),
+            DocumentFormat::Tabloid => (Mm(279.4), Mm(431.8)),
+            DocumentFormat::EnvelopeDL => (Mm(110.0), Mm(220.0)),
+            DocumentFormat::EnvelopeC4 => (Mm(229.0), Mm(324.0)),
+            DocumentFormat::EnvelopeC5 => (Mm(162.0), Mm(229.0)),
+            DocumentFormat::EnvelopeC6 => (Mm(114.0), Mm(162.0)),
+            DocumentFormat::Envelope10 => (Mm(104.8), Mm(241.3)),
+            DocumentFormat::Custom(width, height) => (*width, *height),
         }
     }
 }
 
 impl DocumentOrientation {
-    fn dimensions(&self, size: (Mm, Mm)) -> (Mm, Mm) {
+    /// Applies the orientation to portrait dimensions
+    pub fn dimensions(&self, size: (Mm, Mm)) -> (Mm, Mm) {
         match self {
             DocumentOrientation::Portrait => (size.0, size.1),
             DocumentOrientation::Landscape => (size.1, size.0),
@@ -0,0 +1,44 @@
+use printpdf::Pt;
+
+use crate::generate::{
+    element::{Element, element_builder::ElementBuilder},
+    error::BuildError,
+};
+
+/// Marks the position of an element, so that [`Link::Anchor`](crate::generate::link::Link::Anchor)
+/// links can jump to it.
+pub struct Anchor {
+    name: String,
+    element: Box<dyn Element>,
+}
+
+impl Anchor {
+    pub fn new<E>(name: impl Into<String>, element: E) -> Self
+    where
+        E: Element + 'static,
+    {
+        Self {
+            name: name.into(),
+            element: Box::new(element),
+        }
+    }
+}
+
+impl Element for Anchor {
+    fn display_name(&self) -> &str {
+        "Anchor"
+    }
+
+    fn calculate_width<'a>(&self, builder: &ElementBuilder<'a>) -> Result<Pt, BuildError> {
+        builder.measure_child_width(self.element.as_ref())
+    }
+
+    fn calculate_height<'a>(&self, builder: &ElementBuilder<'a>) -> Result<Pt, BuildError> {
+        builder.measure_child_height(self.element.as_ref())
+    }
+
+    fn build<'a>(&self, builder: &mut ElementBuilder<'a>) -> Result<(), BuildError> {
+        builder.push_anchor(&self.name);
+        builder.build_child(self.element.as_ref())
+    }
+}
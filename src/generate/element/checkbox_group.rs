@@ -2,6 +2,7 @@ use printpdf::{Mm, Pt};
 
 use crate::generate::{
     element::{Element, element_builder::ColumnWidth},
+    error::BuildError,
     font::Font,
     padding::Padding,
 };
@@ -34,28 +35,35 @@ impl Element for CheckboxGroup {
         "Checkbox Group"
     }
 
-    fn calculate_width<'a>(&self, builder: &super::element_builder::ElementBuilder<'a>) -> Pt {
-        self.checkboxes.iter().fold(Pt(0.0), |t, cb| {
-            t + Pt::from(
-                builder.measure_text(cb.as_str(), &self.font).0
-                    + Pt(4.0)
-                    + self.font.font_size()
-                    + self.space_between_checkboxes,
-            )
+    fn calculate_width<'a>(
+        &self,
+        builder: &super::element_builder::ElementBuilder<'a>,
+    ) -> Result<Pt, BuildError> {
+        self.checkboxes.iter().try_fold(Pt(0.0), |t, cb| {
+            Ok(t + builder.measure_text(cb.as_str(), &self.font)?.0
+                + Pt(4.0)
+                + self.font.font_size()
+                + self.space_between_checkboxes)
         })
     }
 
-    fn calculate_height<'a>(&self, _builder: &super::element_builder::ElementBuilder<'a>) -> Pt {
-        self.font.font_size() + self.font.font_height_offset()
+    fn calculate_height<'a>(
+        &self,
+        _builder: &super::element_builder::ElementBuilder<'a>,
+    ) -> Result<Pt, BuildError> {
+        Ok(self.font.font_size() + self.font.font_height_offset())
     }
 
-    fn build<'a>(&self, builder: &mut super::element_builder::ElementBuilder<'a>) {
+    fn build<'a>(
+        &self,
+        builder: &mut super::element_builder::ElementBuilder<'a>,
+    ) -> Result<(), BuildError> {
         // Create a group builder, to have the checkboxes in a group
         // When the checkbox_group is at the bottom of the page, and one of the text has multiple
         // lines and pushes to the next page, we want the whole checkbox_group to be on the next
         // page if possible
         let mut group_builder =
-            builder.generate_group_builder(&Padding::none(), Some(self.calculate_height(builder)));
+            builder.generate_group_builder(&Padding::none(), Some(self.calculate_height(builder)?));
 
         let mut next_builder = group_builder.clone();
 
@@ -63,7 +71,7 @@ impl Element for CheckboxGroup {
         //     .generate_column_builder(ColumnWidth::Percent(1.0 / self.checkboxes.len() as f32));
 
         for item in self.checkboxes.iter() {
-            let width = next_builder.measure_text(&item, &self.font).0
+            let width = next_builder.measure_text(item, &self.font)?.0
                 + self.font.font_size()
                 + Pt(4.0) // Gap between box and text
                 + self.space_between_checkboxes;
@@ -77,7 +85,7 @@ impl Element for CheckboxGroup {
 
             box_builder.draw_rect(self.font.font_size());
 
-            text_builder.push_paragraph(item.as_str(), &self.font);
+            text_builder.push_paragraph(item.as_str(), &self.font)?;
 
             group_builder.merge(box_builder);
             group_builder.merge(text_builder);
@@ -85,10 +93,11 @@ impl Element for CheckboxGroup {
             next_builder = next_side;
         }
 
-        group_builder.advance_cursor(self.calculate_height(builder));
+        group_builder.advance_cursor(self.calculate_height(builder)?);
         let cursor = group_builder.cursor.y;
 
         builder.merge(group_builder);
         builder.update_cursor(cursor);
+        Ok(())
     }
 }
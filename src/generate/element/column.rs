@@ -1,6 +1,9 @@
 use printpdf::{Mm, Pt};
 
-use crate::generate::element::{Element, element_builder::ColumnWidth};
+use crate::generate::{
+    element::{Element, element_builder::ColumnWidth},
+    error::BuildError,
+};
 
 pub const LEFT_WIDTH: Mm = Mm(50.0);
 
@@ -47,19 +50,18 @@ impl Column {
     fn widht_to_column_width<'a>(
         &self,
         builder: &super::element_builder::ElementBuilder<'a>,
-    ) -> ColumnWidth {
-        match self.left_width {
+    ) -> Result<ColumnWidth, BuildError> {
+        Ok(match self.left_width {
             LeftWidth::Percent(p) => ColumnWidth::Percent(p),
             LeftWidth::Fixed(mm) => ColumnWidth::Fixed(mm),
             LeftWidth::Auto => {
-                let left_width = self
-                    .left
-                    .calculate_width(builder)
+                let left_width = builder
+                    .measure_child_width(self.left.as_ref())?
                     .min(builder.remaining_width_from_cursor() - Pt(150.0));
 
                 ColumnWidth::Fixed(Mm::from(left_width))
             }
-        }
+        })
     }
 }
 
@@ -68,25 +70,34 @@ impl Element for Column {
         "Column"
     }
 
-    fn calculate_width<'a>(&self, builder: &super::element_builder::ElementBuilder<'a>) -> Pt {
-        builder.remaining_width_from_cursor()
+    fn calculate_width<'a>(
+        &self,
+        builder: &super::element_builder::ElementBuilder<'a>,
+    ) -> Result<Pt, BuildError> {
+        Ok(builder.remaining_width_from_cursor())
     }
-    fn calculate_height<'a>(&self, builder: &super::element_builder::ElementBuilder<'a>) -> Pt {
-        let column_width = self.widht_to_column_width(builder);
+    fn calculate_height<'a>(
+        &self,
+        builder: &super::element_builder::ElementBuilder<'a>,
+    ) -> Result<Pt, BuildError> {
+        let column_width = self.widht_to_column_width(builder)?;
         let (left_builder, right_builder) = builder.generate_column_builder(column_width);
 
-        let left_height = self.left.calculate_height(&left_builder);
-        let right_height = self.right.calculate_height(&right_builder);
+        let left_height = left_builder.measure_child_height(self.left.as_ref())?;
+        let right_height = right_builder.measure_child_height(self.right.as_ref())?;
 
-        left_height.max(right_height)
+        Ok(left_height.max(right_height))
     }
 
-    fn build<'a>(&self, builder: &mut super::element_builder::ElementBuilder<'a>) {
-        let column_width = self.widht_to_column_width(builder);
+    fn build<'a>(
+        &self,
+        builder: &mut super::element_builder::ElementBuilder<'a>,
+    ) -> Result<(), BuildError> {
+        let column_width = self.widht_to_column_width(builder)?;
 
         let (mut left_builder, mut right_builder) = builder.generate_column_builder(column_width);
-        self.left.build(&mut left_builder);
-        self.right.build(&mut right_builder);
+        left_builder.build_child(self.left.as_ref())?;
+        right_builder.build_child(self.right.as_ref())?;
 
         let new_y = if left_builder.pages.len() == right_builder.pages.len() {
             left_builder.cursor.y.min(right_builder.cursor.y)
@@ -99,5 +110,6 @@ impl Element for Column {
         builder.merge(left_builder);
         builder.merge(right_builder);
         builder.update_cursor(new_y);
+        Ok(())
     }
 }
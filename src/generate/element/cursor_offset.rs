@@ -1,6 +1,6 @@
 use printpdf::Pt;
 
-use crate::generate::{element::Element, font::Font};
+use crate::generate::{element::Element, error::BuildError, font::Font};
 
 pub enum CursorOffset {
     Relative(Pt),
@@ -30,12 +30,18 @@ impl Element for CursorOffset {
         "Cursor Offset"
     }
 
-    fn calculate_width<'a>(&self, _: &super::element_builder::ElementBuilder<'a>) -> Pt {
-        Pt(0.0)
+    fn calculate_width<'a>(
+        &self,
+        _: &super::element_builder::ElementBuilder<'a>,
+    ) -> Result<Pt, BuildError> {
+        Ok(Pt(0.0))
     }
 
-    fn calculate_height<'a>(&self, builder: &super::element_builder::ElementBuilder<'a>) -> Pt {
-        match self {
+    fn calculate_height<'a>(
+        &self,
+        builder: &super::element_builder::ElementBuilder<'a>,
+    ) -> Result<Pt, BuildError> {
+        Ok(match self {
             Self::Relative(rel) => *rel,
             Self::LineBreaks {
                 lines,
@@ -44,12 +50,15 @@ impl Element for CursorOffset {
             } => Pt(*lines as f32 * (font_size.0 + font_height_offset.0)),
             Self::PageBreaks { pages } => {
                 builder.remaining_height_from_cursor()
-                    + Pt((pages - 1) as f32 * builder.document.style().inner_height().into_pt().0)
+                    + Pt((pages - 1) as f32 * builder.page_style().inner_height().into_pt().0)
             }
-        }
+        })
     }
 
-    fn build<'a>(&self, builder: &mut super::element_builder::ElementBuilder<'a>) {
+    fn build<'a>(
+        &self,
+        builder: &mut super::element_builder::ElementBuilder<'a>,
+    ) -> Result<(), BuildError> {
         match self {
             Self::Relative(rel) => {
                 builder.advance_cursor(*rel);
@@ -69,5 +78,6 @@ impl Element for CursorOffset {
         }
 
         builder.reset_cursor_x();
+        Ok(())
     }
 }
@@ -1,18 +1,32 @@
-use std::collections::VecDeque;
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
 
 use printpdf::{
-    Line, LinePoint, Mm, Op, PaintMode, Point, Polygon, Pt, Px, Rect, ShapedText, TextItem,
-    XObject, XObjectTransform,
+    Line, LinePoint, Mm, Op, PaintMode, Point, Polygon, Pt, Rect, ShapedText, TextItem, XObject,
+    XObjectTransform,
 };
 
-use crate::generate::document::Document;
+use crate::generate::bookmark::{Bookmark, PageBookmark};
+use crate::generate::diagnostic::{Diagnostic, DiagnosticKind};
+use crate::generate::document::{Document, DocumentStyle, Page, PageInfo};
 use crate::generate::element::Element;
-use crate::generate::element::image::Image;
+use crate::generate::element::image::{Image, ImageDpi, ImageFit};
 use crate::generate::element::rich_text::{RichText, RichTextLine, RichTextLinePart};
+use crate::generate::error::{BuildError, BuildErrorKind};
 use crate::generate::font::Font;
+use crate::generate::link::{Link, goto_annotation, url_annotation};
 use crate::generate::outline::LineStyle;
 use crate::generate::padding::Padding;
-use crate::generate::text_gen::{shape_text, split_shaped_text};
+use crate::generate::text_gen::{
+    TextAlign, align_shaped_text, has_glyph, resolve_page_placeholders,
+    shape_text_with_letter_spacing, space_width, split_into_font_runs, split_shaped_text,
+};
+use crate::generate::text_style::TextStyle;
+
+/// Used as the maximum width, when text should not be wrapped
+const UNLIMITED_WIDTH: Pt = Pt(100_000.0);
+/// Height of builders that never start a new page
+const UNLIMITED_HEIGHT: Pt = Pt(100_000.0);
 
 #[derive(Debug, Default)]
 pub enum MoveDirection {
@@ -21,18 +35,46 @@ pub enum MoveDirection {
     Down,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TextListStyle {
-    // Bulleted,
-    // Numbered,
+    Bulleted,
+    Numbered(NumberFormat),
     Checkbox,
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum NumberFormat {
+    /// 1. 2. 3.
+    #[default]
+    Decimal,
+    /// a. b. c.
+    LowerAlpha,
+    /// A. B. C.
+    UpperAlpha,
+    /// i. ii. iii.
+    LowerRoman,
+    /// I. II. III.
+    UpperRoman,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum ColumnWidth {
     Fixed(Mm),
     Percent(f32),
 }
 
+/// The size of an image and how it is drawn
+struct ImageLayout {
+    /// Size of the box the image takes up
+    width: Pt,
+    height: Pt,
+    /// Size of the image, centered in the box
+    draw_width: Pt,
+    draw_height: Pt,
+    scale_x: f32,
+    scale_y: f32,
+}
+
 #[derive(Clone)]
 pub struct ElementBuilder<'a> {
     pub(crate) document: &'a Document,
@@ -40,9 +82,17 @@ pub struct ElementBuilder<'a> {
     pub cursor: Point,
     remaining_width: Pt,
     starting_page: usize,
-    pub pages: Vec<Vec<Op>>,
+    pub pages: Vec<Page>,
     added_padding_bottom: Mm,
-    errors: Vec<String>,
+    /// Diagnostics with the index of the page of this builder, they happened on
+    diagnostics: Vec<(usize, Diagnostic)>,
+    /// Only set while rendering headers and footers, when the page count is known.
+    page_info: Option<PageInfo>,
+    /// Whether this builder lays out the pages of the document, and not a header, footer or the
+    /// content of another element
+    top_level: bool,
+    /// Whether a table of contents was built with the entries of the previous layout pass
+    toc_used: bool,
 }
 
 impl<'a> ElementBuilder<'a> {
@@ -59,192 +109,466 @@ impl<'a> ElementBuilder<'a> {
             cursor: origin,
             remaining_width: style.inner_width().into_pt(),
             starting_page: 0,
-            pages: vec![Vec::new()],
+            pages: vec![Page::new(style)],
             added_padding_bottom: Mm(0.0),
-            errors: Vec::new(),
+            diagnostics: Vec::new(),
+            page_info: None,
+            top_level: true,
+            toc_used: false,
+        }
+    }
+
+    /// Creates a builder for the header or footer of a single page.
+    ///
+    /// The builder starts at `origin` and may use the whole page down to the bottom edge, since
+    /// headers and footers are placed inside the page padding.
+    pub(crate) fn new_page_decoration(
+        document: &'a Document,
+        style: &DocumentStyle,
+        origin: Point,
+        page_info: PageInfo,
+    ) -> Self {
+        Self {
+            document,
+            origin,
+            cursor: origin,
+            remaining_width: style.inner_width().into_pt(),
+            starting_page: 0,
+            pages: vec![Page::new(style.clone())],
+            added_padding_bottom: Mm(0.0) - style.padding.bottom,
+            diagnostics: Vec::new(),
+            page_info: Some(page_info),
+            top_level: false,
+            toc_used: false,
         }
     }
+
+    /// The page information, if this builder renders a header or footer.
+    pub fn page_info(&self) -> Option<PageInfo> {
+        self.page_info
+    }
+
+    /// The bookmarks of the document with their page numbers from the previous layout pass.
+    ///
+    /// Building with them makes the document do another pass, when the entries changed.
+    pub(crate) fn use_toc_entries(&mut self) -> &'a [PageBookmark] {
+        self.toc_used = true;
+        self.document.toc_entries()
+    }
+
+    /// Whether this builder or one of the merged builders used the table of contents entries
+    pub(crate) fn toc_used(&self) -> bool {
+        self.toc_used
+    }
+
+    /// Returns the pages and the diagnostics with their page numbers
+    pub(crate) fn finish(self) -> (Vec<Page>, Vec<Diagnostic>) {
+        let diagnostics = self
+            .diagnostics
+            .into_iter()
+            .map(|(page, diagnostic)| diagnostic.on_page(page + 1))
+            .collect();
+
+        (self.pages, diagnostics)
+    }
 }
 
 impl<'a> ElementBuilder<'a> {
-    pub fn measure_text_min_content(&self, text: &str, font: &Font) -> Pt {
-        let shaped_text = shape_text(
+    /// Shapes the text with the given font.
+    ///
+    /// Page placeholders are resolved, when building a header or footer.
+    fn shape(
+        &self,
+        text: &str,
+        font: &Font,
+        max_width: Option<Pt>,
+    ) -> Result<ShapedText, BuildError> {
+        shape_text_with_letter_spacing(
             self.document.pdf_document(),
             font.font_id(),
             font.font_size(),
             font.font_height_offset(),
-            text,
-            None,
-        );
+            font.text_style().letter_spacing,
+            &self.resolve_placeholders(text),
+            max_width,
+        )
+    }
+
+    /// Splits the text into runs of the font and its fallbacks, so that every run can be shaped
+    /// with a single font.
+    fn font_runs(&self, text: &str, font: &Font) -> Vec<(String, Font)> {
+        let text = self.resolve_placeholders(text);
+        if font.fallbacks().is_empty() {
+            return vec![(text.into_owned(), font.clone())];
+        }
+
+        let fonts = std::iter::once(font.font_id())
+            .chain(font.fallbacks().iter().cloned())
+            .collect::<Vec<_>>();
+
+        split_into_font_runs(self.document.pdf_document(), &text, &fonts)
+            .into_iter()
+            .map(|(run, index)| (run, font.fallback_font(fonts[index].clone())))
+            .collect()
+    }
+
+    /// Whether the text contains characters, that are only available in a fallback font
+    fn needs_fallback(&self, text: &str, font: &Font) -> bool {
+        !font.fallbacks().is_empty() && self.font_runs(text, font).len() > 1
+    }
+
+    /// Measures text with fallback fonts by splitting it into lines like rich text
+    fn measure_fallback_text(
+        &self,
+        text: &str,
+        font: &Font,
+        max_width: Pt,
+    ) -> Result<(Pt, Pt), BuildError> {
+        let lines = self.split_parts_into_lines(&[(text.to_string(), font.clone())], max_width)?;
+
+        Ok(lines
+            .iter()
+            .fold((Pt(0.0), Pt(0.0)), |(width, height), line| {
+                (
+                    width.max(self.rich_text_line_metrics(line).0),
+                    height + line.height,
+                )
+            }))
+    }
+
+    /// Reports the characters, that neither the font nor its fallbacks have a glyph for
+    fn check_glyphs(&mut self, text: &str, font: &Font) {
+        let doc = self.document.pdf_document();
+        let mut missing = self
+            .resolve_placeholders(text)
+            .chars()
+            .filter(|c| !c.is_whitespace() && !c.is_control())
+            .filter(|c| {
+                !has_glyph(doc, &font.font_id(), *c)
+                    && !font.fallbacks().iter().any(|id| has_glyph(doc, id, *c))
+            })
+            .collect::<Vec<_>>();
+        if missing.is_empty() {
+            return;
+        }
+
+        missing.sort_unstable();
+        missing.dedup();
+        self.push_diagnostic(Diagnostic::new(
+            DiagnosticKind::Font,
+            format!(
+                "No glyph for {}",
+                missing
+                    .iter()
+                    .map(|c| format!("'{c}'"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        ));
+    }
+
+    fn resolve_placeholders<'t>(&self, text: &'t str) -> Cow<'t, str> {
+        match self.page_info {
+            Some(page_info) => resolve_page_placeholders(text, page_info),
+            None => Cow::Borrowed(text),
+        }
+    }
+
+    pub fn measure_text_min_content(&self, text: &str, font: &Font) -> Result<Pt, BuildError> {
+        if self.needs_fallback(text, font) {
+            return self
+                .font_runs(text, font)
+                .iter()
+                .map(|(run, run_font)| self.measure_text_min_content(run, run_font))
+                .try_fold(Pt(0.0), |width, run_width| Ok(width.max(run_width?)));
+        }
 
-        shaped_text
+        let shaped_text = self.shape(text, font, None)?;
+
+        Ok(shaped_text
             .lines
             .iter()
             .flat_map(|line| line.words.iter())
             .map(|word| Pt(word.width))
             .max()
-            .unwrap_or_else(|| Pt(0.0))
+            .unwrap_or(Pt(0.0)))
     }
 
-    pub fn measure_text_manuel(&self, text: &str, font: &Font, max_width: Option<Pt>) -> (Pt, Pt) {
-        let no_limit_shaped_text = shape_text(
-            self.document.pdf_document(),
-            font.font_id(),
-            font.font_size(),
-            font.font_height_offset(),
-            text,
-            None,
-        );
+    pub fn measure_text_manuel(
+        &self,
+        text: &str,
+        font: &Font,
+        max_width: Option<Pt>,
+    ) -> Result<(Pt, Pt), BuildError> {
+        if self.needs_fallback(text, font) {
+            return self.measure_fallback_text(text, font, max_width.unwrap_or(UNLIMITED_WIDTH));
+        }
 
-        if let Some(max_width) = max_width {
-            if Pt(no_limit_shaped_text.width) > max_width {
-                let shaped_text = shape_text(
-                    self.document.pdf_document(),
-                    font.font_id(),
-                    font.font_size(),
-                    font.font_height_offset(),
-                    text,
-                    Some(max_width),
-                );
+        let no_limit_shaped_text = self.shape(text, font, None)?;
 
-                return (Pt(shaped_text.width), Pt(shaped_text.height));
-            }
+        if let Some(max_width) = max_width
+            && Pt(no_limit_shaped_text.width) > max_width
+        {
+            let shaped_text = self.shape(text, font, Some(max_width))?;
+
+            return Ok((Pt(shaped_text.width), Pt(shaped_text.height)));
         }
-        (
+        Ok((
             Pt(no_limit_shaped_text.width),
             Pt(no_limit_shaped_text.height),
-        )
+        ))
     }
 
-    pub fn measure_text(&self, text: &str, font: &Font) -> (Pt, Pt) {
-        let no_limit_shaped_text = shape_text(
-            self.document.pdf_document(),
-            font.font_id(),
+    /// Height of the lines of the text, that fit into `max_height`. The text is only split between
+    /// lines, like at the end of a page.
+    pub fn fitting_text_height(
+        &self,
+        text: &str,
+        font: &Font,
+        max_width: Pt,
+        max_height: Pt,
+    ) -> Result<Pt, BuildError> {
+        if self.needs_fallback(text, font) {
+            let lines =
+                self.split_parts_into_lines(&[(text.to_string(), font.clone())], max_width)?;
+
+            let mut height = Pt(0.0);
+            for line in lines {
+                if height + line.height > max_height {
+                    break;
+                }
+                height += line.height;
+            }
+            return Ok(height);
+        }
+
+        let shaped_text = self.shape(text, font, Some(max_width))?;
+        let (first, _) = split_shaped_text(
+            shaped_text,
             font.font_size(),
             font.font_height_offset(),
-            text,
-            None,
+            max_height,
         );
 
+        Ok(Pt(first.height))
+    }
+
+    pub fn measure_text(&self, text: &str, font: &Font) -> Result<(Pt, Pt), BuildError> {
+        if self.needs_fallback(text, font) {
+            return self.measure_fallback_text(text, font, self.remaining_width_from_cursor());
+        }
+
+        let no_limit_shaped_text = self.shape(text, font, None)?;
+
         if Pt(no_limit_shaped_text.width) > self.remaining_width_from_cursor() {
-            let shaped_text = shape_text(
-                self.document.pdf_document(),
-                font.font_id(),
-                font.font_size(),
-                font.font_height_offset(),
-                text,
-                Some(self.remaining_width_from_cursor()),
-            );
+            let shaped_text = self.shape(text, font, Some(self.remaining_width_from_cursor()))?;
 
-            (Pt(shaped_text.width), Pt(shaped_text.height))
+            Ok((Pt(shaped_text.width), Pt(shaped_text.height)))
         } else {
-            (
+            Ok((
                 Pt(no_limit_shaped_text.width),
                 Pt(no_limit_shaped_text.height),
-            )
+            ))
         }
     }
 
-    pub fn first_line(&self, text: &str, width: Pt, font: &Font) -> String {
+    pub fn first_line(&self, text: &str, width: Pt, font: &Font) -> Result<String, BuildError> {
         if text.is_empty() {
-            return String::new();
+            return Ok(String::new());
         }
 
-        let shaped_text = shape_text(
-            self.document.pdf_document(),
-            font.font_id(),
-            font.font_size(),
-            font.font_height_offset(),
-            text,
-            Some(width),
-        );
+        let shaped_text = self.shape(text, font, Some(width))?;
 
         let Some(first_line) = shaped_text.lines.first() else {
-            return String::new();
+            return Ok(String::new());
         };
 
-        first_line
+        Ok(first_line
             .words
             .iter()
             .map(|w| w.text.as_str())
             .collect::<Vec<_>>()
-            .join("")
+            .join(""))
     }
 
-    pub fn measure_image(&self, image: &Image) -> (Pt, Pt) {
-        let XObject::Image(raw_image) = self
+    fn image_layout(&self, image: &Image) -> Result<ImageLayout, BuildError> {
+        let Some((xobject_width, xobject_height)) = self
             .document
             .pdf_document()
             .resources
             .xobjects
             .map
             .get(&image.image)
-            .expect("Image not found in document resources")
+            .and_then(XObject::get_width_height)
         else {
-            panic!("Expected XObject to be an Image");
+            return Err(BuildErrorKind::ImageNotFound(image.image.clone()).into());
+        };
+
+        // Downsampled images keep the size of the original
+        let info = self.document.image_info(&image.image);
+        let (px_width, px_height) = info.map_or((xobject_width, xobject_height), |info| {
+            (info.width, info.height)
+        });
+        let dpi = match image.dpi {
+            ImageDpi::Fixed(dpi) => dpi,
+            ImageDpi::Native => info.and_then(|info| info.dpi).unwrap_or(300.0),
         };
+        let width = px_width.into_pt(dpi);
+        let height = px_height.into_pt(dpi);
+
+        let desired_width = image.desired_width.map(|width| width.into_pt());
+        let desired_height = image.desired_height.map(|height| height.into_pt());
+        let (box_width, box_height, draw_width, draw_height) = match (desired_width, desired_height)
+        {
+            (None, None) => (width, height, width, height),
+            (Some(box_width), None) => {
+                let box_height = height * (box_width / width);
+                (box_width, box_height, box_width, box_height)
+            }
+            (None, Some(box_height)) => {
+                let box_width = width * (box_height / height);
+                (box_width, box_height, box_width, box_height)
+            }
+            (Some(box_width), Some(box_height)) => {
+                let scale_x = box_width / width;
+                let scale_y = box_height / height;
+                let scale = match image.fit {
+                    ImageFit::Fill => None,
+                    ImageFit::Contain => Some(scale_x.min(scale_y)),
+                    ImageFit::Cover => Some(scale_x.max(scale_y)),
+                };
 
-        let width = Px(raw_image.width).into_pt(300.0);
-        let height = Px(raw_image.height).into_pt(300.0);
+                match scale {
+                    Some(scale) => (box_width, box_height, width * scale, height * scale),
+                    None => (box_width, box_height, box_width, box_height),
+                }
+            }
+        };
 
-        let scale = image
-            .desired_width
-            .map(|desired_width| desired_width.into_pt() / width);
+        let limit =
+            |max: Option<Mm>, size: Pt| max.map_or(1.0, |max| (max.into_pt() / size).min(1.0));
+        let shrink = limit(image.max_width, box_width).min(limit(image.max_height, box_height));
+
+        // The XObject is drawn at 300 DPI without scaling
+        Ok(ImageLayout {
+            width: box_width * shrink,
+            height: box_height * shrink,
+            draw_width: draw_width * shrink,
+            draw_height: draw_height * shrink,
+            scale_x: draw_width * shrink / xobject_width.into_pt(300.0),
+            scale_y: draw_height * shrink / xobject_height.into_pt(300.0),
+        })
+    }
 
-        let final_width = scale.map(|scale| width * scale).unwrap_or(width);
-        let final_height = scale.map(|scale| height * scale).unwrap_or(height);
+    pub fn measure_image(&self, image: &Image) -> Result<(Pt, Pt), BuildError> {
+        let layout = self.image_layout(image)?;
+        Ok((layout.width, layout.height))
+    }
 
-        (final_width, final_height)
+    pub fn push_paragraph(&mut self, paragraph: &str, font: &Font) -> Result<(), BuildError> {
+        self.push_paragraph_aligned(paragraph, font, TextAlign::Left)
     }
 
-    pub fn push_paragraph(&mut self, paragraph: &str, font: &Font) {
-        let shaped_text = shape_text(
-            self.document.pdf_document(),
-            font.font_id(),
-            font.font_size(),
-            font.font_height_offset(),
-            paragraph,
-            Some(self.remaining_width_from_cursor()),
+    /// Pushes the paragraph with every line aligned within the remaining width
+    pub fn push_paragraph_aligned(
+        &mut self,
+        paragraph: &str,
+        font: &Font,
+        align: TextAlign,
+    ) -> Result<(), BuildError> {
+        self.check_glyphs(paragraph, font);
+        let width = self.remaining_width_from_cursor();
+
+        if self.needs_fallback(paragraph, font) {
+            let lines =
+                self.split_parts_into_lines(&[(paragraph.to_string(), font.clone())], width)?;
+            self.push_rich_text_lines(lines, width, align, &HashMap::new());
+            return Ok(());
+        }
+
+        let mut shaped_text = self.shape(paragraph, font, Some(width))?;
+        align_shaped_text(
+            &mut shaped_text,
+            width,
+            align,
+            space_width(
+                self.document.pdf_document(),
+                &font.font_id(),
+                font.font_size(),
+            ),
         );
 
-        self.push_shaped_text(shaped_text, font.font_size(), font.font_height_offset());
+        self.push_shaped_text(shaped_text, font);
+        Ok(())
     }
 
     /// Returning the last shaped text that didn't fit
-    fn push_shaped_text(&mut self, text: ShapedText, font_size: Pt, font_height_offset: Pt) {
+    fn push_shaped_text(&mut self, text: ShapedText, font: &Font) {
         // Do we need to cut the text?
         let (first, rest) = split_shaped_text(
             text,
-            font_size,
-            font_height_offset,
+            font.font_size(),
+            font.font_height_offset(),
             self.remaining_height_from_cursor(),
         );
 
-        let ops = first.get_ops(Point {
-            x: self.cursor.x,
-            y: self.cursor.y,
-        });
+        let ops = Self::styled_shaped_text_ops(
+            &first,
+            font,
+            Point {
+                x: self.cursor.x,
+                y: self.cursor.y,
+            },
+        );
         self.pages
             .last_mut()
             .expect("We always have one page")
-            .extend(ops.into_iter());
+            .extend(ops);
         self.cursor.y -= Pt(first.height);
 
         if let Some(rest) = rest {
-            println!(
-                "Text didn't fit, pushing to next page. Rest height {:?}",
-                rest.lines
-                    .first()
-                    .unwrap()
-                    .words
-                    .iter()
-                    .map(|w| w.text.clone())
-                    .collect::<Vec<_>>()
-                    .join("")
-            );
             self.next_page();
-            self.push_shaped_text(rest, font_size, font_height_offset);
+            self.push_shaped_text(rest, font);
+        }
+    }
+
+    /// The ops of the shaped text with the text style of the font applied.
+    ///
+    /// `origin` is the top left corner of the text.
+    fn styled_shaped_text_ops(text: &ShapedText, font: &Font, origin: Point) -> Vec<Op> {
+        let style = font.text_style();
+        let mut ops = text.get_ops(origin);
+        if *style == TextStyle::default() {
+            return ops;
+        }
+
+        // The text ops start with saving the graphics state, the text state belongs after it
+        ops.splice(1..1, style.text_state_ops());
+
+        let line_height = font.font_size() + font.font_height_offset();
+        let mut background = Vec::new();
+        for (index, line) in text.lines.iter().enumerate() {
+            let mut visible_words = line
+                .words
+                .iter()
+                .filter(|word| !word.text.trim().is_empty());
+            let Some(first) = visible_words.next() else {
+                continue;
+            };
+            let last = visible_words.next_back().unwrap_or(first);
+
+            let baseline = Point {
+                x: origin.x + Pt(line.x + first.x),
+                y: origin.y - font.font_size() - line_height * index as f32,
+            };
+            let width = Pt(last.x + last.width - first.x);
+
+            background.extend(style.background_ops(baseline, width, font.font_size(), line_height));
+            ops.extend(style.foreground_ops(baseline, width, font.font_size()));
         }
+
+        background.extend(ops);
+        background
     }
 
     pub fn draw_rect(&mut self, size: Pt) {
@@ -286,37 +610,37 @@ impl<'a> ElementBuilder<'a> {
         let width =
             self.remaining_width_from_cursor() - padding.left.into_pt() - padding.right.into_pt();
 
-        let mut ops = Vec::new();
-        ops.push(Op::SaveGraphicsState);
-        ops.push(Op::SetOutlineColor {
-            col: printpdf::Color::Rgb(outline.color.clone()),
-        });
-        ops.push(Op::SetOutlineThickness {
-            pt: outline.thickness,
-        });
-        ops.push(Op::DrawLine {
-            line: printpdf::Line {
-                points: vec![
-                    LinePoint {
-                        bezier: false,
-                        p: Point {
-                            x: self.cursor.x + padding.left.into_pt(),
-                            y: self.cursor.y,
+        let ops = vec![
+            Op::SaveGraphicsState,
+            Op::SetOutlineColor {
+                col: printpdf::Color::Rgb(outline.color.clone()),
+            },
+            Op::SetOutlineThickness {
+                pt: outline.thickness,
+            },
+            Op::DrawLine {
+                line: printpdf::Line {
+                    points: vec![
+                        LinePoint {
+                            bezier: false,
+                            p: Point {
+                                x: self.cursor.x + padding.left.into_pt(),
+                                y: self.cursor.y,
+                            },
                         },
-                    },
-                    LinePoint {
-                        bezier: false,
-                        p: Point {
-                            x: self.cursor.x + width + padding.left.into_pt(),
-                            y: self.cursor.y,
+                        LinePoint {
+                            bezier: false,
+                            p: Point {
+                                x: self.cursor.x + width + padding.left.into_pt(),
+                                y: self.cursor.y,
+                            },
                         },
-                    },
-                ],
-                is_closed: false,
+                    ],
+                    is_closed: false,
+                },
             },
-        });
-
-        ops.push(Op::RestoreGraphicsState);
+            Op::RestoreGraphicsState,
+        ];
 
         self.pages
             .last_mut()
@@ -331,7 +655,7 @@ impl<'a> ElementBuilder<'a> {
         elements: impl IntoIterator<Item = Box<&'element (impl Element + 'element)>>,
         space_x: Pt,
         space_y: Pt,
-    ) -> Pt {
+    ) -> Result<Pt, BuildError> {
         let remaining_width = self.remaining_width_from_cursor();
 
         let mut x_cursor = self.cursor.x;
@@ -339,7 +663,7 @@ impl<'a> ElementBuilder<'a> {
         let mut current_line_height = Pt(0.0);
 
         for element in elements.into_iter() {
-            let width = element.calculate_width(self);
+            let width = self.measure_child_width(*element)?;
             if width > remaining_width {
                 // The element wont fit at all. We skip it. In the rendering, we will generate an
                 // error
@@ -356,10 +680,11 @@ impl<'a> ElementBuilder<'a> {
             }
             x_cursor += width + space_x;
 
-            current_line_height = current_line_height.max(element.calculate_height(self));
+            let height = self.measure_child_height(*element)?;
+            current_line_height = current_line_height.max(height);
         }
 
-        current_line_height + current_measured_height
+        Ok(current_line_height + current_measured_height)
     }
 
     /// Flex will try and order elements on the x axis first, before going to the next line.
@@ -368,19 +693,25 @@ impl<'a> ElementBuilder<'a> {
         elements: impl Iterator<Item = Box<&'e (impl Element + 'e)>>,
         space_x: Pt,
         space_y: Pt,
-    ) {
+    ) -> Result<(), BuildError> {
         let remaining_width = self.remaining_width_from_cursor();
 
         let mut current_line_height = Pt(0.0);
         for element in elements {
-            let width = element.calculate_width(self);
-            let height = element.calculate_height(self);
+            let width = self.measure_child_width(*element)?;
+            let height = self.measure_child_height(*element)?;
             if width > remaining_width {
-                // This element wont fit at all. We skip it. Also we generate an error
-                self.errors.push(format!(
-                    "Failed to generate flex item \"{}\"",
-                    element.display_name()
-                ));
+                // This element wont fit at all. We skip it and report it
+                self.push_diagnostic(
+                    Diagnostic::new(
+                        DiagnosticKind::SkippedFlexItem,
+                        format!(
+                            "{:.1}pt wide, but only {:.1}pt are available",
+                            width.0, remaining_width.0
+                        ),
+                    )
+                    .in_element(element.display_name()),
+                );
                 continue;
             }
 
@@ -399,43 +730,49 @@ impl<'a> ElementBuilder<'a> {
 
             current_line_height = current_line_height.max(height);
 
-            element.build(self);
+            self.build_child(*element)?;
 
             self.cursor.x += space_x;
         }
 
         self.advance_cursor(current_line_height);
         self.reset_cursor_x();
+        Ok(())
     }
 
-    pub fn push_image(&mut self, image: &Image) {
-        let XObject::Image(raw_image) = self
-            .document
-            .pdf_document()
-            .resources
-            .xobjects
-            .map
-            .get(&image.image)
-            .expect("Image not found in document resources")
-        else {
-            panic!("Expected XObject to be an Image");
-        };
-
-        let width = Px(raw_image.width).into_pt(300.0);
-        let height = Px(raw_image.height).into_pt(300.0);
+    pub fn push_image(&mut self, image: &Image) -> Result<(), BuildError> {
+        let layout = self.image_layout(image)?;
+        let (final_width, final_height) = (layout.width, layout.height);
 
-        let scale = image
-            .desired_width
-            .map(|desired_width| desired_width.into_pt() / width);
+        if final_width > self.remaining_width_from_cursor()
+            || final_height > self.remaining_height_from_cursor()
+        {
+            self.push_diagnostic(Diagnostic::new(
+                DiagnosticKind::Overflow,
+                format!(
+                    "The image is {:.1}pt x {:.1}pt, but only {:.1}pt x {:.1}pt are available",
+                    final_width.0,
+                    final_height.0,
+                    self.remaining_width_from_cursor().0,
+                    self.remaining_height_from_cursor().0
+                ),
+            ));
+        }
 
-        let final_width = scale.map(|scale| width * scale).unwrap_or(width);
-        let final_height = scale.map(|scale| height * scale).unwrap_or(height);
+        // Covering images are larger than their box
+        let clip = layout.draw_width > final_width || layout.draw_height > final_height;
+        if clip {
+            self.begin_clip(final_width, final_height);
+        }
 
+        // Centered in the box
         let transform = XObjectTransform {
-            translate_x: Some(self.cursor.x),
-            translate_y: Some(self.cursor.y - final_height),
-            scale_x: scale,
-            scale_y: scale,
+            translate_x: Some(self.cursor.x + (final_width - layout.draw_width) / 2.0),
+            translate_y: Some(
+                self.cursor.y - final_height + (final_height - layout.draw_height) / 2.0,
+            ),
+            scale_x: Some(layout.scale_x),
+            scale_y: Some(layout.scale_y),
             ..Default::default()
         };
 
@@ -447,9 +784,114 @@ impl<'a> ElementBuilder<'a> {
         self.pages
             .last_mut()
             .expect("We always have one page")
-            .extend(ops.into_iter());
+            .extend(ops);
+
+        if clip {
+            self.end_clip();
+        }
+
+        if let Some(link) = &image.link {
+            self.push_link(
+                Rect {
+                    x: self.cursor.x,
+                    y: self.cursor.y - final_height,
+                    width: final_width,
+                    height: final_height,
+                },
+                link,
+            );
+        }
 
         self.cursor.x += final_width;
+        Ok(())
+    }
+
+    /// Reports a problem on the current page, that didn't stop the element from being built
+    pub fn push_diagnostic(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push((self.pages.len() - 1, diagnostic));
+    }
+
+    /// Measures the width of a child element.
+    ///
+    /// Its display name is added to the path of its errors.
+    pub fn measure_child_width<E: Element + ?Sized>(&self, element: &E) -> Result<Pt, BuildError> {
+        Self::measure_child(element, element.calculate_width(self))
+    }
+
+    /// Measures the height of a child element.
+    ///
+    /// Its display name is added to the path of its errors.
+    pub fn measure_child_height<E: Element + ?Sized>(&self, element: &E) -> Result<Pt, BuildError> {
+        Self::measure_child(element, element.calculate_height(self))
+    }
+
+    fn measure_child<E: Element + ?Sized>(
+        element: &E,
+        measured: Result<Pt, BuildError>,
+    ) -> Result<Pt, BuildError> {
+        measured.map_err(|e| e.in_element(element.display_name()))
+    }
+
+    /// Builds a child element.
+    ///
+    /// Its display name is added to the path of its errors and diagnostics.
+    pub fn build_child<E: Element + ?Sized>(&mut self, element: &E) -> Result<(), BuildError> {
+        let start = self.diagnostics.len();
+        let result = element
+            .build(self)
+            .map_err(|e| e.in_element(element.display_name()));
+
+        for (_, diagnostic) in self.diagnostics[start..].iter_mut() {
+            diagnostic
+                .path
+                .insert(0, element.display_name().to_string());
+        }
+
+        result
+    }
+
+    /// Marks the current cursor position, so that links to `name` jump here
+    pub fn push_anchor(&mut self, name: &str) {
+        let y = self.cursor.y;
+        self.pages
+            .last_mut()
+            .expect("We always have one page")
+            .anchors
+            .push((name.to_string(), y));
+    }
+
+    /// Adds an entry to the document outline at the current cursor position
+    pub fn push_bookmark(&mut self, title: &str, level: u8) {
+        let y = self.cursor.y;
+        self.pages
+            .last_mut()
+            .expect("We always have one page")
+            .bookmarks
+            .push(Bookmark {
+                title: title.to_string(),
+                level,
+                y,
+            });
+    }
+
+    /// Makes the area on the current page clickable.
+    ///
+    /// `rect` is given with its lower left corner, like annotations in the PDF.
+    pub fn push_link(&mut self, rect: Rect, link: &Link) {
+        let page = self.pages.last_mut().expect("We always have one page");
+
+        match link {
+            Link::Url(url) => page.push(url_annotation(rect, url)),
+            Link::Anchor(name) => page.anchor_links.push((rect, name.clone())),
+        }
+    }
+
+    /// Makes the area on the current page jump to the position `y` on `page`, starting at 1
+    pub(crate) fn push_page_link(&mut self, rect: Rect, page: usize, y: Pt) {
+        self.pages
+            .last_mut()
+            .expect("We always have one page")
+            .push(goto_annotation(rect, page, y));
     }
 
     pub fn generate_column_builder(
@@ -468,9 +910,12 @@ impl<'a> ElementBuilder<'a> {
             cursor: self.cursor,
             remaining_width: left_width,
             starting_page: self.pages.len() - 1,
-            pages: vec![Vec::new()],
+            pages: vec![Page::new(self.page_style().clone())],
             added_padding_bottom: Mm(0.0),
-            errors: Vec::new(),
+            diagnostics: Vec::new(),
+            page_info: self.page_info,
+            top_level: false,
+            toc_used: false,
         };
         let right_origin = Point {
             x: self.cursor.x + left_width,
@@ -483,9 +928,12 @@ impl<'a> ElementBuilder<'a> {
             cursor: right_origin,
             remaining_width: right_width,
             starting_page: self.pages.len() - 1,
-            pages: vec![Vec::new()],
+            pages: vec![Page::new(self.page_style().clone())],
             added_padding_bottom: Mm(0.0),
-            errors: Vec::new(),
+            diagnostics: Vec::new(),
+            page_info: self.page_info,
+            top_level: false,
+            toc_used: false,
         };
 
         (left_builder, right_builder)
@@ -506,15 +954,13 @@ impl<'a> ElementBuilder<'a> {
     ) -> ElementBuilder<'a> {
         let (origin, new_page) = match try_same_page {
             Some(height)
-                if height <= self.document.style().inner_height().into_pt()
+                if height <= self.page_style().inner_height().into_pt()
                     && self.remaining_height_from_cursor() < height =>
             {
                 // We can fit the group on a single page, but need to go to the next
                 let origin = Point {
                     x: self.origin.x + padding.left.into_pt(),
-                    y: (self.document.style().height
-                        - self.document.style().padding.top
-                        - padding.top)
+                    y: (self.page_style().height - self.page_style().padding.top - padding.top)
                         .into_pt(),
                 };
                 (origin, true)
@@ -535,167 +981,88 @@ impl<'a> ElementBuilder<'a> {
             remaining_width: self.remaining_width - (padding.left + padding.right).into_pt(),
             // This seems to mess with the new pages, when creatin a checkbox group that will be pushed to the next page
             starting_page: self.pages.len() - if new_page { 0 } else { 1 },
-            pages: vec![Vec::new()],
+            pages: vec![Page::new(self.page_style().clone())],
             added_padding_bottom: padding.bottom,
-            errors: Vec::new(),
+            diagnostics: Vec::new(),
+            page_info: self.page_info,
+            top_level: false,
+            toc_used: false,
         }
     }
 
-    pub fn draw_outline(&mut self, padding: &Padding, outline: &LineStyle) {
-        let width = self.remaining_width + (padding.left + padding.right).into_pt();
-
-        {
-            // Draw the top
+    /// Generates a builder for the content of a box, e.g. a table cell, starting at the offset
+    /// from the cursor.
+    ///
+    /// The builder never starts a new page, content below the box should be clipped with
+    /// [`ElementBuilder::begin_clip`].
+    pub fn generate_cell_builder(&self, offset: Point, width: Pt) -> ElementBuilder<'a> {
+        let origin = Point {
+            x: self.cursor.x + offset.x,
+            y: self.cursor.y - offset.y,
+        };
 
-            let mut ops = vec![];
-            ops.push(Op::SaveGraphicsState);
+        ElementBuilder {
+            document: self.document,
+            origin,
+            cursor: origin,
+            remaining_width: width,
+            starting_page: self.pages.len() - 1,
+            pages: vec![Page::new(self.page_style().clone())],
+            added_padding_bottom: Mm(0.0) - UNLIMITED_HEIGHT.into(),
+            diagnostics: Vec::new(),
+            page_info: self.page_info,
+            top_level: false,
+            toc_used: false,
+        }
+    }
 
-            ops.push(Op::SetOutlineColor {
-                col: printpdf::Color::Rgb(outline.color.clone()),
-            });
-            ops.push(Op::SetOutlineThickness {
-                pt: outline.thickness,
-            });
-            ops.push(Op::DrawLine {
-                line: Line {
-                    points: vec![
-                        LinePoint {
-                            p: Point {
-                                x: self.origin.x - padding.left.into_pt(),
-                                y: self.origin.y + padding.top.into_pt(),
-                            },
-                            bezier: false,
-                        },
-                        LinePoint {
-                            p: Point {
-                                x: self.origin.x - padding.left.into_pt() + width,
-                                y: self.origin.y + padding.top.into_pt(),
-                            },
-                            bezier: false,
-                        },
-                    ],
-                    is_closed: false,
-                },
-            });
-
-            ops.push(Op::RestoreGraphicsState);
-
-            self.pages.first_mut().expect("Always one page").extend(ops);
-        }
-
-        {
-            // Draw bottom
-
-            let mut ops = vec![];
-            ops.push(Op::SaveGraphicsState);
-
-            ops.push(Op::SetOutlineColor {
-                col: printpdf::Color::Rgb(outline.color.clone()),
-            });
-            ops.push(Op::SetOutlineThickness {
-                pt: outline.thickness,
-            });
-            ops.push(Op::DrawLine {
-                line: Line {
-                    points: vec![
-                        LinePoint {
-                            p: Point {
-                                x: self.cursor.x - padding.left.into_pt(),
-                                y: self.cursor.y - padding.bottom.into_pt(),
-                            },
-                            bezier: false,
-                        },
-                        LinePoint {
-                            p: Point {
-                                x: self.cursor.x - padding.left.into_pt() + width,
-                                y: self.cursor.y - padding.bottom.into_pt(),
-                            },
-                            bezier: false,
-                        },
-                    ],
-                    is_closed: false,
+    /// Everything drawn on the current page until [`ElementBuilder::end_clip`] is only visible
+    /// inside the rectangle below the cursor
+    pub fn begin_clip(&mut self, width: Pt, height: Pt) {
+        let rect = Rect {
+            x: self.cursor.x,
+            y: self.cursor.y,
+            width,
+            height,
+        };
+        let ops = [
+            Op::SaveGraphicsState,
+            Op::DrawPolygon {
+                polygon: Polygon {
+                    mode: PaintMode::Clip,
+                    ..rect.to_polygon()
                 },
-            });
-
-            ops.push(Op::RestoreGraphicsState);
-
-            self.pages
-                .last_mut()
-                .expect("Always have at least one page")
-                .extend(ops);
-        }
+            },
+        ];
 
-        // Draw left and right lines
-        // First if the whole builder is a single page we can just connect the lines
-        if self.pages.len() == 1 {
-            let mut ops = vec![];
-            ops.push(Op::SaveGraphicsState);
+        self.pages
+            .last_mut()
+            .expect("Always at least one page")
+            .extend(ops);
+    }
 
-            ops.push(Op::SetOutlineColor {
-                col: printpdf::Color::Rgb(outline.color.clone()),
-            });
-            ops.push(Op::SetOutlineThickness {
-                pt: outline.thickness,
-            });
-            ops.push(Op::DrawLine {
-                line: Line {
-                    points: vec![
-                        LinePoint {
-                            p: Point {
-                                x: self.origin.x - padding.left.into_pt(),
-                                y: self.origin.y + padding.top.into_pt(),
-                            },
-                            bezier: false,
-                        },
-                        LinePoint {
-                            p: Point {
-                                x: self.cursor.x - padding.left.into_pt(),
-                                y: self.cursor.y - padding.bottom.into_pt(),
-                            },
-                            bezier: false,
-                        },
-                    ],
-                    is_closed: false,
-                },
-            });
-            ops.push(Op::DrawLine {
-                line: Line {
-                    points: vec![
-                        LinePoint {
-                            p: Point {
-                                x: self.origin.x - padding.left.into_pt() + width,
-                                y: self.origin.y + padding.top.into_pt(),
-                            },
-                            bezier: false,
-                        },
-                        LinePoint {
-                            p: Point {
-                                x: self.cursor.x - padding.left.into_pt() + width,
-                                y: self.cursor.y - padding.bottom.into_pt(),
-                            },
-                            bezier: false,
-                        },
-                    ],
-                    is_closed: false,
-                },
-            });
+    pub fn end_clip(&mut self) {
+        self.pages
+            .last_mut()
+            .expect("Always at least one page")
+            .push(Op::RestoreGraphicsState);
+    }
 
-            ops.push(Op::RestoreGraphicsState);
+    pub fn draw_outline(&mut self, padding: &Padding, outline: &LineStyle) {
+        let width = self.remaining_width + (padding.left + padding.right).into_pt();
 
-            self.pages[0].extend(ops);
-        } else {
-            // Draw till the end on the first page
-            {
-                let mut ops = vec![];
-                ops.push(Op::SaveGraphicsState);
+        {
+            // Draw the top
 
-                ops.push(Op::SetOutlineColor {
+            let ops = vec![
+                Op::SaveGraphicsState,
+                Op::SetOutlineColor {
                     col: printpdf::Color::Rgb(outline.color.clone()),
-                });
-                ops.push(Op::SetOutlineThickness {
+                },
+                Op::SetOutlineThickness {
                     pt: outline.thickness,
-                });
-                ops.push(Op::DrawLine {
+                },
+                Op::DrawLine {
                     line: Line {
                         points: vec![
                             LinePoint {
@@ -707,61 +1074,80 @@ impl<'a> ElementBuilder<'a> {
                             },
                             LinePoint {
                                 p: Point {
-                                    x: self.cursor.x - padding.left.into_pt(),
-                                    y: self.document.style().padding.bottom.into_pt(),
+                                    x: self.origin.x - padding.left.into_pt() + width,
+                                    y: self.origin.y + padding.top.into_pt(),
                                 },
                                 bezier: false,
                             },
                         ],
                         is_closed: false,
                     },
-                });
-                ops.push(Op::DrawLine {
+                },
+                Op::RestoreGraphicsState,
+            ];
+
+            self.pages.first_mut().expect("Always one page").extend(ops);
+        }
+
+        {
+            // Draw bottom
+
+            let ops = vec![
+                Op::SaveGraphicsState,
+                Op::SetOutlineColor {
+                    col: printpdf::Color::Rgb(outline.color.clone()),
+                },
+                Op::SetOutlineThickness {
+                    pt: outline.thickness,
+                },
+                Op::DrawLine {
                     line: Line {
                         points: vec![
                             LinePoint {
                                 p: Point {
-                                    x: self.origin.x - padding.left.into_pt() + width,
-                                    y: self.origin.y + padding.top.into_pt(),
+                                    x: self.cursor.x - padding.left.into_pt(),
+                                    y: self.cursor.y - padding.bottom.into_pt(),
                                 },
                                 bezier: false,
                             },
                             LinePoint {
                                 p: Point {
                                     x: self.cursor.x - padding.left.into_pt() + width,
-                                    y: self.document.style().padding.bottom.into_pt(),
+                                    y: self.cursor.y - padding.bottom.into_pt(),
                                 },
                                 bezier: false,
                             },
                         ],
                         is_closed: false,
                     },
-                });
-
-                ops.push(Op::RestoreGraphicsState);
-
-                self.pages[0].extend(ops);
-            }
+                },
+                Op::RestoreGraphicsState,
+            ];
 
-            {
-                // Draw last to beginning of the page
-                let mut ops = vec![];
-                ops.push(Op::SaveGraphicsState);
+            self.pages
+                .last_mut()
+                .expect("Always have at least one page")
+                .extend(ops);
+        }
 
-                ops.push(Op::SetOutlineColor {
+        // Draw left and right lines
+        // First if the whole builder is a single page we can just connect the lines
+        if self.pages.len() == 1 {
+            let ops = vec![
+                Op::SaveGraphicsState,
+                Op::SetOutlineColor {
                     col: printpdf::Color::Rgb(outline.color.clone()),
-                });
-                ops.push(Op::SetOutlineThickness {
+                },
+                Op::SetOutlineThickness {
                     pt: outline.thickness,
-                });
-                ops.push(Op::DrawLine {
+                },
+                Op::DrawLine {
                     line: Line {
                         points: vec![
                             LinePoint {
                                 p: Point {
                                     x: self.origin.x - padding.left.into_pt(),
-                                    y: self.document.style().height.into_pt()
-                                        - self.document.style().padding.top.into_pt(),
+                                    y: self.origin.y + padding.top.into_pt(),
                                 },
                                 bezier: false,
                             },
@@ -775,15 +1161,14 @@ impl<'a> ElementBuilder<'a> {
                         ],
                         is_closed: false,
                     },
-                });
-                ops.push(Op::DrawLine {
+                },
+                Op::DrawLine {
                     line: Line {
                         points: vec![
                             LinePoint {
                                 p: Point {
                                     x: self.origin.x - padding.left.into_pt() + width,
-                                    y: self.document.style().height.into_pt()
-                                        - self.document.style().padding.top.into_pt(),
+                                    y: self.origin.y + padding.top.into_pt(),
                                 },
                                 bezier: false,
                             },
@@ -797,78 +1182,196 @@ impl<'a> ElementBuilder<'a> {
                         ],
                         is_closed: false,
                     },
-                });
+                },
+                Op::RestoreGraphicsState,
+            ];
 
-                ops.push(Op::RestoreGraphicsState);
+            self.pages[0].extend(ops);
+        } else {
+            let first_style = self.pages[0].style.clone();
+            let last_style = self.page_style().clone();
 
-                self.pages
-                    .last_mut()
-                    .expect("At least one page")
-                    .extend(ops);
-            }
+            // Draw till the end on the first page
+            {
+                let ops = vec![
+                    Op::SaveGraphicsState,
+                    Op::SetOutlineColor {
+                        col: printpdf::Color::Rgb(outline.color.clone()),
+                    },
+                    Op::SetOutlineThickness {
+                        pt: outline.thickness,
+                    },
+                    Op::DrawLine {
+                        line: Line {
+                            points: vec![
+                                LinePoint {
+                                    p: Point {
+                                        x: self.origin.x - padding.left.into_pt(),
+                                        y: self.origin.y + padding.top.into_pt(),
+                                    },
+                                    bezier: false,
+                                },
+                                LinePoint {
+                                    p: Point {
+                                        x: self.cursor.x - padding.left.into_pt(),
+                                        y: first_style.padding.bottom.into_pt(),
+                                    },
+                                    bezier: false,
+                                },
+                            ],
+                            is_closed: false,
+                        },
+                    },
+                    Op::DrawLine {
+                        line: Line {
+                            points: vec![
+                                LinePoint {
+                                    p: Point {
+                                        x: self.origin.x - padding.left.into_pt() + width,
+                                        y: self.origin.y + padding.top.into_pt(),
+                                    },
+                                    bezier: false,
+                                },
+                                LinePoint {
+                                    p: Point {
+                                        x: self.cursor.x - padding.left.into_pt() + width,
+                                        y: first_style.padding.bottom.into_pt(),
+                                    },
+                                    bezier: false,
+                                },
+                            ],
+                            is_closed: false,
+                        },
+                    },
+                    Op::RestoreGraphicsState,
+                ];
 
-            if self.pages.len() > 2 {
-                // Draw the middle pages
-                let mut ops = vec![];
-                ops.push(Op::SaveGraphicsState);
+                self.pages[0].extend(ops);
+            }
 
-                ops.push(Op::SetOutlineColor {
-                    col: printpdf::Color::Rgb(outline.color.clone()),
-                });
-                ops.push(Op::SetOutlineThickness {
-                    pt: outline.thickness,
-                });
-                ops.push(Op::DrawLine {
-                    line: Line {
-                        points: vec![
-                            LinePoint {
-                                p: Point {
-                                    x: self.origin.x - padding.left.into_pt(),
-                                    y: self.document.style().height.into_pt()
-                                        - self.document.style().padding.top.into_pt(),
+            {
+                // Draw last to beginning of the page
+                let ops = vec![
+                    Op::SaveGraphicsState,
+                    Op::SetOutlineColor {
+                        col: printpdf::Color::Rgb(outline.color.clone()),
+                    },
+                    Op::SetOutlineThickness {
+                        pt: outline.thickness,
+                    },
+                    Op::DrawLine {
+                        line: Line {
+                            points: vec![
+                                LinePoint {
+                                    p: Point {
+                                        x: self.origin.x - padding.left.into_pt(),
+                                        y: last_style.height.into_pt()
+                                            - last_style.padding.top.into_pt(),
+                                    },
+                                    bezier: false,
                                 },
-                                bezier: false,
-                            },
-                            LinePoint {
-                                p: Point {
-                                    x: self.cursor.x - padding.left.into_pt(),
-                                    y: self.document.style().padding.bottom.into_pt(),
+                                LinePoint {
+                                    p: Point {
+                                        x: self.cursor.x - padding.left.into_pt(),
+                                        y: self.cursor.y - padding.bottom.into_pt(),
+                                    },
+                                    bezier: false,
                                 },
-                                bezier: false,
-                            },
-                        ],
-                        is_closed: false,
+                            ],
+                            is_closed: false,
+                        },
                     },
-                });
-                ops.push(Op::DrawLine {
-                    line: Line {
-                        points: vec![
-                            LinePoint {
-                                p: Point {
-                                    x: self.origin.x - padding.left.into_pt() + width,
-                                    y: self.document.style().height.into_pt()
-                                        - self.document.style().padding.top.into_pt(),
+                    Op::DrawLine {
+                        line: Line {
+                            points: vec![
+                                LinePoint {
+                                    p: Point {
+                                        x: self.origin.x - padding.left.into_pt() + width,
+                                        y: last_style.height.into_pt()
+                                            - last_style.padding.top.into_pt(),
+                                    },
+                                    bezier: false,
                                 },
-                                bezier: false,
-                            },
-                            LinePoint {
-                                p: Point {
-                                    x: self.cursor.x - padding.left.into_pt() + width,
-                                    y: self.document.style().padding.bottom.into_pt(),
+                                LinePoint {
+                                    p: Point {
+                                        x: self.cursor.x - padding.left.into_pt() + width,
+                                        y: self.cursor.y - padding.bottom.into_pt(),
+                                    },
+                                    bezier: false,
                                 },
-                                bezier: false,
-                            },
-                        ],
-                        is_closed: false,
+                            ],
+                            is_closed: false,
+                        },
                     },
-                });
+                    Op::RestoreGraphicsState,
+                ];
 
-                ops.push(Op::RestoreGraphicsState);
+                self.pages
+                    .last_mut()
+                    .expect("At least one page")
+                    .extend(ops);
+            }
 
+            if self.pages.len() > 2 {
+                // Draw the middle pages
                 let num_pages = self.pages.len();
 
                 for p in self.pages.iter_mut().skip(1).take(num_pages - 2) {
-                    p.extend(ops.clone());
+                    let style = &p.style;
+                    let ops = vec![
+                        Op::SaveGraphicsState,
+                        Op::SetOutlineColor {
+                            col: printpdf::Color::Rgb(outline.color.clone()),
+                        },
+                        Op::SetOutlineThickness {
+                            pt: outline.thickness,
+                        },
+                        Op::DrawLine {
+                            line: Line {
+                                points: vec![
+                                    LinePoint {
+                                        p: Point {
+                                            x: self.origin.x - padding.left.into_pt(),
+                                            y: style.height.into_pt() - style.padding.top.into_pt(),
+                                        },
+                                        bezier: false,
+                                    },
+                                    LinePoint {
+                                        p: Point {
+                                            x: self.cursor.x - padding.left.into_pt(),
+                                            y: style.padding.bottom.into_pt(),
+                                        },
+                                        bezier: false,
+                                    },
+                                ],
+                                is_closed: false,
+                            },
+                        },
+                        Op::DrawLine {
+                            line: Line {
+                                points: vec![
+                                    LinePoint {
+                                        p: Point {
+                                            x: self.origin.x - padding.left.into_pt() + width,
+                                            y: style.height.into_pt() - style.padding.top.into_pt(),
+                                        },
+                                        bezier: false,
+                                    },
+                                    LinePoint {
+                                        p: Point {
+                                            x: self.cursor.x - padding.left.into_pt() + width,
+                                            y: style.padding.bottom.into_pt(),
+                                        },
+                                        bezier: false,
+                                    },
+                                ],
+                                is_closed: false,
+                            },
+                        },
+                        Op::RestoreGraphicsState,
+                    ];
+
+                    p.extend(ops);
                 }
             }
         }
@@ -903,7 +1406,7 @@ impl<'a> ElementBuilder<'a> {
 
     pub fn remaining_height_from_cursor(&self) -> Pt {
         self.cursor.y
-            - self.document.style().padding.bottom.into_pt()
+            - self.page_style().padding.bottom.into_pt()
             - self.added_padding_bottom.into_pt()
     }
 
@@ -916,14 +1419,51 @@ impl<'a> ElementBuilder<'a> {
     /// Recalculates remaining_height
     /// remaining_width stays the same
     pub(crate) fn next_page(&mut self) {
-        let style = self.document.style().clone();
+        let style = self.page_style().clone();
         let origin = Point {
             x: self.origin.x,
             y: (style.height - style.padding.top).into_pt(),
         };
 
         self.cursor = origin;
-        self.pages.push(Vec::new());
+        self.pages.push(Page::new(style));
+    }
+
+    /// The page setup of the current page
+    pub fn page_style(&self) -> &DocumentStyle {
+        &self.pages.last().expect("We always have one page").style
+    }
+
+    /// Continues on a new page with a different page setup.
+    ///
+    /// All following pages will use this style, until the next section is started. If nothing
+    /// has been drawn on the current page yet, the current page is reused.
+    ///
+    /// Sections change the page setup of the whole document, so they can only be started by the
+    /// builder of the document, not inside a header, footer or another element.
+    pub fn start_section(&mut self, style: DocumentStyle) -> Result<(), BuildError> {
+        if !self.top_level {
+            return Err(BuildErrorKind::NestedSection.into());
+        }
+
+        let current = self.page_style().clone();
+        self.origin.x = style.padding.left.into_pt();
+        self.remaining_width = style.inner_width().into_pt();
+
+        let top = (current.height - current.padding.top).into_pt();
+        let page = self.pages.last_mut().expect("We always have one page");
+        if page.is_empty() && self.cursor.y >= top {
+            page.style = style.clone();
+        } else {
+            self.pages.push(Page::new(style.clone()));
+        }
+
+        self.cursor = Point {
+            x: self.origin.x,
+            y: (style.height - style.padding.top).into_pt(),
+        };
+
+        Ok(())
     }
 
     pub fn merge(&mut self, other: ElementBuilder) {
@@ -931,7 +1471,7 @@ impl<'a> ElementBuilder<'a> {
 
         for p in self.pages[other.starting_page..].iter_mut() {
             if let Some(extend) = dequeue.pop_front() {
-                p.extend(extend);
+                p.append(extend);
             } else {
                 break;
             }
@@ -941,38 +1481,56 @@ impl<'a> ElementBuilder<'a> {
             self.pages.push(next);
         }
 
-        self.errors.extend(other.errors);
+        self.toc_used |= other.toc_used;
+        self.diagnostics.extend(
+            other
+                .diagnostics
+                .into_iter()
+                .map(|(page, diagnostic)| (page + other.starting_page, diagnostic)),
+        );
+    }
+
+    pub fn split_rich_text_into_lines(
+        &self,
+        rich_text: &RichText,
+    ) -> Result<Vec<RichTextLine>, BuildError> {
+        self.split_parts_into_lines(&rich_text.parts, self.remaining_width)
     }
 
-    pub fn split_rich_text_into_lines(&self, rich_text: &RichText) -> Vec<RichTextLine> {
+    /// Splits text parts with different fonts into lines, that fit into `max_width`.
+    ///
+    /// Parts with characters that are missing in their font are split further into runs of their
+    /// fallback fonts.
+    pub fn split_parts_into_lines(
+        &self,
+        parts: &[(String, Font)],
+        max_width: Pt,
+    ) -> Result<Vec<RichTextLine>, BuildError> {
         let mut current_line_height = Pt(0.0);
         let mut current_line_width = Pt(0.0);
         let mut lines: Vec<RichTextLine> = vec![RichTextLine::default()];
 
-        for (text, font) in rich_text.parts.iter() {
+        let parts = parts
+            .iter()
+            .enumerate()
+            .flat_map(|(part_index, (text, font))| {
+                self.font_runs(text, font)
+                    .into_iter()
+                    .map(move |(text, font)| (part_index, text, font))
+            })
+            .collect::<Vec<_>>();
+
+        for (part_index, text, font) in parts.iter() {
             if text.is_empty() {
                 continue;
             }
 
-            let shaped_text = shape_text(
-                self.document.pdf_document(),
-                font.font_id(),
-                font.font_size(),
-                font.font_height_offset(),
-                &text,
-                Some(self.remaining_width - current_line_width),
-            );
+            let text = text.as_str();
+
+            let shaped_text = self.shape(text, font, Some(max_width - current_line_width))?;
 
             let width = Pt(if shaped_text.lines.len() == 1 {
-                shape_text(
-                    self.document.pdf_document(),
-                    font.font_id(),
-                    font.font_size(),
-                    font.font_height_offset(),
-                    &text,
-                    None,
-                )
-                .width
+                self.shape(text, font, None)?.width
             } else {
                 shaped_text
                     .lines
@@ -984,31 +1542,16 @@ impl<'a> ElementBuilder<'a> {
                     .sum()
             });
 
-            let (width, shaped_text) = if width > self.remaining_width - current_line_width {
+            let (width, shaped_text) = if width > max_width - current_line_width {
                 // If we cant fit on the current line anymore, we go to the next line.
                 current_line_width = Pt(0.0);
                 current_line_height = Pt(0.0);
                 lines.push(RichTextLine::default());
 
-                let shaped_text = shape_text(
-                    self.document.pdf_document(),
-                    font.font_id(),
-                    font.font_size(),
-                    font.font_height_offset(),
-                    &text,
-                    Some(self.remaining_width - current_line_width),
-                );
+                let shaped_text = self.shape(text, font, Some(max_width - current_line_width))?;
 
                 let width = Pt(if shaped_text.lines.len() == 1 {
-                    shape_text(
-                        self.document.pdf_document(),
-                        font.font_id(),
-                        font.font_size(),
-                        font.font_height_offset(),
-                        &text,
-                        None,
-                    )
-                    .width
+                    self.shape(text, font, None)?.width
                 } else {
                     shaped_text
                         .lines
@@ -1028,7 +1571,7 @@ impl<'a> ElementBuilder<'a> {
             let height = font.font_size() + font.font_height_offset();
 
             let line_text = if shaped_text.lines.len() == 1 {
-                text.clone()
+                text.to_string()
             } else {
                 shaped_text
                     .lines
@@ -1049,6 +1592,7 @@ impl<'a> ElementBuilder<'a> {
                 text: line_text,
                 font: font.clone(),
                 width,
+                part_index: *part_index,
             });
 
             current_line_height = current_line_height.max(height);
@@ -1059,14 +1603,7 @@ impl<'a> ElementBuilder<'a> {
             if shaped_text.lines.len() > 1 {
                 current_line_width = Pt(0.0);
                 // Check the rest of the lines
-                let shaped_rest = shape_text(
-                    self.document.pdf_document(),
-                    font.font_id(),
-                    font.font_size(),
-                    font.font_height_offset(),
-                    rest_text,
-                    Some(self.remaining_width),
-                );
+                let shaped_rest = self.shape(rest_text, font, Some(max_width))?;
 
                 for (index, line) in shaped_rest.lines.iter().enumerate() {
                     let mut line_text = line
@@ -1080,18 +1617,12 @@ impl<'a> ElementBuilder<'a> {
 
                     if index == shaped_rest.lines.len() - 1 {
                         // Count spaces at the end of the rest_text
-                        let parsed_font = self
-                            .document
-                            .pdf_document()
-                            .resources
-                            .fonts
-                            .map
-                            .get(&font.font_id())
-                            .expect("Font not loaded");
-
-                        let space_width = parsed_font.get_space_width().unwrap_or_default() as f32
-                            / parsed_font.font_metrics.units_per_em as f32
-                            * font.font_size().0;
+                        let space_width = space_width(
+                            self.document.pdf_document(),
+                            &font.font_id(),
+                            font.font_size(),
+                        )
+                        .0;
 
                         let spaces_at_end =
                             rest_text.chars().rev().take_while(|&c| c == ' ').count() as f32;
@@ -1106,6 +1637,7 @@ impl<'a> ElementBuilder<'a> {
                             text: line_text,
                             font: font.clone(),
                             width: Pt(width),
+                            part_index: *part_index,
                         }],
                         height: Pt(font.font_size().0 + font.font_height_offset().0),
                     });
@@ -1114,7 +1646,7 @@ impl<'a> ElementBuilder<'a> {
             }
         }
 
-        lines
+        Ok(lines)
     }
 
     pub fn push_text_dont_change_cursor(
@@ -1123,23 +1655,111 @@ impl<'a> ElementBuilder<'a> {
         font: &Font,
         offset: Point,
         max_width: Option<Pt>,
-    ) {
-        let shaped_text = shape_text(
-            self.document.pdf_document(),
-            font.font_id(),
-            font.font_size(),
-            font.font_height_offset(),
-            text,
-            max_width,
+    ) -> Result<(), BuildError> {
+        self.push_text_aligned_dont_change_cursor(text, font, offset, max_width, TextAlign::Left)
+    }
+
+    /// Pushes the text with every line aligned within `max_width`. Without a maximum width, the
+    /// text is always aligned left.
+    pub fn push_text_aligned_dont_change_cursor(
+        &mut self,
+        text: &str,
+        font: &Font,
+        offset: Point,
+        max_width: Option<Pt>,
+        align: TextAlign,
+    ) -> Result<(), BuildError> {
+        self.check_glyphs(text, font);
+        let align = if max_width.is_some() {
+            align
+        } else {
+            TextAlign::Left
+        };
+
+        if self.needs_fallback(text, font) {
+            let width = max_width.unwrap_or(UNLIMITED_WIDTH);
+            let lines = self.split_parts_into_lines(&[(text.to_string(), font.clone())], width)?;
+
+            let mut origin = Point {
+                x: self.cursor.x + offset.x,
+                y: self.cursor.y - offset.y,
+            };
+            let mut ops = Vec::new();
+            for line in lines {
+                origin.y -= line.height;
+                let (line_ops, _) =
+                    self.rich_text_line_ops(line, origin, width, align, true, &HashMap::new());
+                ops.extend(line_ops);
+            }
+
+            self.pages
+                .last_mut()
+                .expect("Always at least one page")
+                .extend(ops);
+            return Ok(());
+        }
+
+        let mut shaped_text = self.shape(text, font, max_width)?;
+        if let Some(width) = max_width {
+            align_shaped_text(
+                &mut shaped_text,
+                width,
+                align,
+                space_width(
+                    self.document.pdf_document(),
+                    &font.font_id(),
+                    font.font_size(),
+                ),
+            );
+        }
+        let ops = Self::styled_shaped_text_ops(
+            &shaped_text,
+            font,
+            Point {
+                x: self.cursor.x + offset.x,
+                y: self.cursor.y - offset.y,
+            },
         );
 
         self.pages
             .last_mut()
             .expect("Always at least one page")
-            .extend(shaped_text.get_ops(Point {
-                x: self.cursor.x + offset.x,
-                y: self.cursor.y - offset.y,
-            }));
+            .extend(ops);
+        Ok(())
+    }
+
+    /// Draws a straight line between two points on the current page
+    pub fn stroke_line(&mut self, from: Point, to: Point, line_style: &LineStyle) {
+        let ops = vec![
+            Op::SaveGraphicsState,
+            Op::SetOutlineColor {
+                col: printpdf::Color::Rgb(line_style.color.clone()),
+            },
+            Op::SetOutlineThickness {
+                pt: line_style.thickness,
+            },
+            Op::DrawLine {
+                line: Line {
+                    points: vec![
+                        LinePoint {
+                            p: from,
+                            bezier: false,
+                        },
+                        LinePoint {
+                            p: to,
+                            bezier: false,
+                        },
+                    ],
+                    is_closed: false,
+                },
+            },
+            Op::RestoreGraphicsState,
+        ];
+
+        self.pages
+            .last_mut()
+            .expect("Always at least one page")
+            .extend(ops);
     }
 
     pub fn fill_rect_dont_change_cursor(&mut self, width: Pt, height: Pt, color: printpdf::Color) {
@@ -1171,56 +1791,236 @@ impl<'a> ElementBuilder<'a> {
             .extend(ops);
     }
 
-    pub fn push_rich_text(&mut self, rich_text: &crate::generate::element::rich_text::RichText) {
+    pub fn push_rich_text(
+        &mut self,
+        rich_text: &crate::generate::element::rich_text::RichText,
+    ) -> Result<(), BuildError> {
+        for (text, font) in rich_text.parts.iter() {
+            self.check_glyphs(text, font);
+        }
+
         // We first need to cut into lines, so we can calculate the height properly
-        let lines = self.split_rich_text_into_lines(rich_text);
+        let lines = self.split_rich_text_into_lines(rich_text)?;
+        let width = self.remaining_width_from_cursor();
+
+        self.push_rich_text_lines(lines, width, rich_text.align, &rich_text.links);
+        self.advance_cursor(Pt(3.0));
+        Ok(())
+    }
+
+    /// `links` are the links of the parts by their index
+    fn push_rich_text_lines(
+        &mut self,
+        lines: Vec<RichTextLine>,
+        width: Pt,
+        align: TextAlign,
+        links: &HashMap<usize, Link>,
+    ) {
+        let line_count = lines.len();
 
-        for line in lines.into_iter() {
+        for (index, line) in lines.into_iter().enumerate() {
             self.advance_cursor(line.height);
-            for part in line.parts {
-                let ops = Self::get_ops(&part.text, &part.font, self.cursor);
-                self.pages
-                    .last_mut()
-                    .expect("Always have one page")
-                    .extend(ops);
-                self.cursor.x += part.width;
+
+            let (ops, line_links) = self.rich_text_line_ops(
+                line,
+                self.cursor,
+                width,
+                align,
+                index + 1 == line_count,
+                links,
+            );
+            self.pages
+                .last_mut()
+                .expect("Always have one page")
+                .extend(ops);
+
+            for (rect, link) in line_links {
+                self.push_link(rect, &link);
             }
-            self.reset_cursor_x();
         }
-        self.advance_cursor(Pt(3.0));
     }
 
-    fn get_ops(text: &str, font: &Font, origin: Point) -> Vec<Op> {
-        let line_height = font.font_height_offset() + font.font_size();
-        let font_size = font.font_size();
+    /// Returns the width of the line without trailing spaces and the number of spaces between
+    /// the words.
+    fn rich_text_line_metrics(&self, line: &RichTextLine) -> (Pt, usize) {
+        // Spaces at the end of the line are not visible and don't count for the alignment
+        let trailing_spaces = line
+            .parts
+            .iter()
+            .rev()
+            .flat_map(|part| part.text.chars().rev())
+            .take_while(|&c| c == ' ')
+            .count();
+        let inner_spaces = line
+            .parts
+            .iter()
+            .map(|part| part.text.matches(' ').count())
+            .sum::<usize>()
+            - trailing_spaces;
+
+        let trailing_width = line.parts.last().map_or(Pt(0.0), |part| {
+            space_width(
+                self.document.pdf_document(),
+                &part.font.font_id(),
+                part.font.font_size(),
+            ) * trailing_spaces as f32
+        });
+        let line_width = line
+            .parts
+            .iter()
+            .fold(Pt(0.0), |width, part| width + part.width)
+            - trailing_width;
+
+        (line_width, inner_spaces)
+    }
+
+    /// Generates the ops of a single line, with its baseline starting at `origin`.
+    ///
+    /// Also returns the areas of the parts that have a link in `links`.
+    fn rich_text_line_ops(
+        &self,
+        line: RichTextLine,
+        origin: Point,
+        width: Pt,
+        align: TextAlign,
+        is_last_line: bool,
+        links: &HashMap<usize, Link>,
+    ) -> (Vec<Op>, Vec<(Rect, Link)>) {
+        let (line_width, mut inner_spaces) = self.rich_text_line_metrics(&line);
+        let free = (width - line_width).max(Pt(0.0));
+
+        let mut cursor = origin;
+        let mut word_gap = Pt(0.0);
+        match align {
+            TextAlign::Left => {}
+            TextAlign::Center => cursor.x += free / 2.0,
+            TextAlign::Right => cursor.x += free,
+            TextAlign::Justify => {
+                if !is_last_line && inner_spaces > 0 {
+                    word_gap = free / inner_spaces as f32;
+                }
+            }
+        }
 
+        let part_count = line.parts.len();
         let mut ops = Vec::new();
+        let mut link_areas = Vec::new();
+        for (index, part) in line.parts.into_iter().enumerate() {
+            let stretched_spaces = part.text.matches(' ').count().min(inner_spaces);
+            inner_spaces -= stretched_spaces;
+
+            let style = part.font.text_style();
+            let font_size = part.font.font_size();
+            let mut visible_width = part.width + word_gap * stretched_spaces as f32;
+            if index + 1 == part_count {
+                // Trailing spaces of the line are not decorated
+                let trailing_spaces = part.text.chars().rev().take_while(|&c| c == ' ').count();
+                visible_width -= space_width(
+                    self.document.pdf_document(),
+                    &part.font.font_id(),
+                    font_size,
+                ) * trailing_spaces as f32;
+            }
 
-        ops.push(Op::SaveGraphicsState);
+            ops.extend(style.background_ops(
+                cursor,
+                visible_width,
+                font_size,
+                font_size + part.font.font_height_offset(),
+            ));
+            ops.extend(Self::get_ops(
+                &part.text,
+                &part.font,
+                cursor,
+                word_gap,
+                stretched_spaces,
+            ));
+            ops.extend(style.foreground_ops(cursor, visible_width, font_size));
+
+            if let Some(link) = links.get(&part.part_index) {
+                let line_height = font_size + part.font.font_height_offset();
+                link_areas.push((
+                    Rect {
+                        x: cursor.x,
+                        y: cursor.y - (line_height - font_size),
+                        width: visible_width,
+                        height: line_height,
+                    },
+                    link.clone(),
+                ));
+            }
 
-        // Start text section
-        ops.push(Op::StartTextSection);
+            cursor.x += part.width + word_gap * stretched_spaces as f32;
+        }
 
-        // The origin_TOP_LEFT is the top left origin of the entire text block being layouted
-        // However, in PDF, the "set text cursor" sets the baseline of the first line...
-        ops.push(Op::SetTextCursor { pos: origin });
+        (ops, link_areas)
+    }
 
-        ops.push(Op::SetFontSize {
-            size: font_size,
-            font: font.font_id(),
-        });
+    /// Splits the text at its spaces, so that `word_gap` is added after the first
+    /// `stretched_spaces` spaces.
+    fn justified_text_items(
+        text: &str,
+        font_size: Pt,
+        word_gap: Pt,
+        stretched_spaces: usize,
+    ) -> Vec<TextItem> {
+        if word_gap <= Pt(0.0) || stretched_spaces == 0 {
+            return vec![TextItem::Text(text.to_string())];
+        }
 
-        ops.push(Op::SetLineHeight { lh: line_height });
+        // Offsets are given in thousandths of the font size, negative values move to the right
+        let offset = -(word_gap.0 / font_size.0 * 1000.0);
+
+        let mut items = Vec::new();
+        let mut current = String::new();
+        let mut remaining = stretched_spaces;
+        for c in text.chars() {
+            current.push(c);
+            if c == ' ' && remaining > 0 {
+                items.push(TextItem::Text(std::mem::take(&mut current)));
+                items.push(TextItem::Offset(offset));
+                remaining -= 1;
+            }
+        }
 
-        ops.push(Op::WriteText {
-            items: vec![TextItem::Text(text.to_string())],
-            font: font.font_id(),
-        });
+        if !current.is_empty() {
+            items.push(TextItem::Text(current));
+        }
+
+        items
+    }
 
-        // End text section
-        ops.push(Op::EndTextSection);
+    fn get_ops(
+        text: &str,
+        font: &Font,
+        origin: Point,
+        word_gap: Pt,
+        stretched_spaces: usize,
+    ) -> Vec<Op> {
+        let line_height = font.font_height_offset() + font.font_size();
+        let font_size = font.font_size();
 
-        ops.push(Op::RestoreGraphicsState);
+        let mut ops = vec![Op::SaveGraphicsState];
+        ops.extend(font.text_style().text_state_ops());
+        ops.extend([
+            // Start text section
+            Op::StartTextSection,
+            // The origin_TOP_LEFT is the top left origin of the entire text block being layouted
+            // However, in PDF, the "set text cursor" sets the baseline of the first line...
+            Op::SetTextCursor { pos: origin },
+            Op::SetFontSize {
+                size: font_size,
+                font: font.font_id(),
+            },
+            Op::SetLineHeight { lh: line_height },
+            Op::WriteText {
+                items: Self::justified_text_items(text, font_size, word_gap, stretched_spaces),
+                font: font.font_id(),
+            },
+            // End text section
+            Op::EndTextSection,
+            Op::RestoreGraphicsState,
+        ]);
 
         ops
     }
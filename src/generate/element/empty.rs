@@ -1,6 +1,6 @@
 use printpdf::Pt;
 
-use crate::generate::element::Element;
+use crate::generate::{element::Element, error::BuildError};
 
 pub struct Empty;
 
@@ -9,13 +9,24 @@ impl Element for Empty {
         "Empty"
     }
 
-    fn calculate_height<'a>(&self, _: &super::element_builder::ElementBuilder<'a>) -> Pt {
-        Pt(0.0)
+    fn calculate_height<'a>(
+        &self,
+        _: &super::element_builder::ElementBuilder<'a>,
+    ) -> Result<Pt, BuildError> {
+        Ok(Pt(0.0))
     }
 
-    fn calculate_width<'a>(&self, _: &super::element_builder::ElementBuilder<'a>) -> Pt {
-        Pt(0.0)
+    fn calculate_width<'a>(
+        &self,
+        _: &super::element_builder::ElementBuilder<'a>,
+    ) -> Result<Pt, BuildError> {
+        Ok(Pt(0.0))
     }
 
-    fn build<'a>(&self, _: &mut super::element_builder::ElementBuilder<'a>) {}
+    fn build<'a>(
+        &self,
+        _: &mut super::element_builder::ElementBuilder<'a>,
+    ) -> Result<(), BuildError> {
+        Ok(())
+    }
 }
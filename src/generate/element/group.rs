@@ -1,6 +1,6 @@
 use printpdf::Pt;
 
-use crate::generate::{element::Element, outline::LineStyle, padding::Padding};
+use crate::generate::{element::Element, error::BuildError, outline::LineStyle, padding::Padding};
 
 pub struct Group {
     pub elements: Vec<Box<dyn Element>>,
@@ -9,6 +9,12 @@ pub struct Group {
     pub try_keep_together: bool,
 }
 
+impl Default for Group {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Group {
     pub fn new() -> Self {
         Group {
@@ -47,37 +53,47 @@ impl Element for Group {
         "Group"
     }
 
-    fn calculate_width<'a>(&self, builder: &super::element_builder::ElementBuilder<'a>) -> Pt {
+    fn calculate_width<'a>(
+        &self,
+        builder: &super::element_builder::ElementBuilder<'a>,
+    ) -> Result<Pt, BuildError> {
         let group_builder = builder.generate_group_builder(&self.padding, None);
 
-        self.elements
-            .iter()
-            .map(|elem| elem.calculate_width(&group_builder))
-            .fold(Pt(0.0), |v, h| v + h)
-            + self.padding.left.into_pt()
-            + self.padding.right.into_pt()
+        Ok(self.elements.iter().try_fold(Pt(0.0), |v, elem| {
+            group_builder
+                .measure_child_width(elem.as_ref())
+                .map(|w| v + w)
+        })? + self.padding.left.into_pt()
+            + self.padding.right.into_pt())
     }
 
-    fn calculate_height<'a>(&self, builder: &super::element_builder::ElementBuilder<'a>) -> Pt {
+    fn calculate_height<'a>(
+        &self,
+        builder: &super::element_builder::ElementBuilder<'a>,
+    ) -> Result<Pt, BuildError> {
         // We need to compute the text height with the padding of the group
         let group_builder = builder.generate_group_builder(&self.padding, None);
 
-        self.elements
-            .iter()
-            .map(|elem| elem.calculate_height(&group_builder))
-            .fold(Pt(0.0), |v, h| v + h)
-            + self.padding.top.into_pt()
-            + self.padding.bottom.into_pt()
+        Ok(self.elements.iter().try_fold(Pt(0.0), |v, elem| {
+            group_builder
+                .measure_child_height(elem.as_ref())
+                .map(|h| v + h)
+        })? + self.padding.top.into_pt()
+            + self.padding.bottom.into_pt())
     }
 
-    fn build<'a>(&self, builder: &mut super::element_builder::ElementBuilder<'a>) {
+    fn build<'a>(
+        &self,
+        builder: &mut super::element_builder::ElementBuilder<'a>,
+    ) -> Result<(), BuildError> {
         let height = self
             .try_keep_together
-            .then(|| self.calculate_height(builder));
+            .then(|| self.calculate_height(builder))
+            .transpose()?;
         let mut group_builder = builder.generate_group_builder(&self.padding, height);
 
         for child in &self.elements {
-            child.build(&mut group_builder);
+            group_builder.build_child(child.as_ref())?;
         }
 
         if let Some(outline) = &self.outline {
@@ -88,5 +104,6 @@ impl Element for Group {
 
         builder.merge(group_builder);
         builder.update_cursor(new_y);
+        Ok(())
     }
 }
@@ -0,0 +1,67 @@
+use printpdf::Pt;
+
+use crate::generate::{
+    element::{Element, element_builder::ElementBuilder},
+    error::BuildError,
+    font::Font,
+    text_gen::TextAlign,
+};
+
+/// A title that is added to the outline of the document.
+///
+/// Headings with a higher level are nested below the previous heading with a lower level.
+pub struct Heading {
+    text: String,
+    font: Font,
+    level: u8,
+    align: TextAlign,
+}
+
+impl Heading {
+    pub fn new(text: impl Into<String>, font: Font) -> Self {
+        Self {
+            text: text.into(),
+            font,
+            level: 1,
+            align: TextAlign::Left,
+        }
+    }
+
+    /// The nesting level in the outline, starting at 1
+    pub fn with_level(mut self, level: u8) -> Self {
+        self.level = level.max(1);
+        self
+    }
+
+    pub fn with_align(mut self, align: TextAlign) -> Self {
+        self.align = align;
+        self
+    }
+}
+
+impl Element for Heading {
+    fn display_name(&self) -> &str {
+        "Heading"
+    }
+
+    fn calculate_width<'a>(&self, builder: &ElementBuilder<'a>) -> Result<Pt, BuildError> {
+        Ok(builder.measure_text(self.text.as_str(), &self.font)?.0)
+    }
+
+    fn calculate_height<'a>(&self, builder: &ElementBuilder<'a>) -> Result<Pt, BuildError> {
+        Ok(builder.measure_text(self.text.as_str(), &self.font)?.1)
+    }
+
+    fn build<'a>(&self, builder: &mut ElementBuilder<'a>) -> Result<(), BuildError> {
+        // Headings are not split, so the bookmark points to the page the text is on
+        let height = self.calculate_height(builder)?;
+        if height > builder.remaining_height_from_cursor()
+            && height <= builder.page_style().inner_height().into_pt()
+        {
+            builder.next_page();
+        }
+
+        builder.push_bookmark(&self.text, self.level);
+        builder.push_paragraph_aligned(self.text.as_str(), &self.font, self.align)
+    }
+}
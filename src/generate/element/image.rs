@@ -1,10 +1,44 @@
 use printpdf::{Mm, Pt, XObjectId};
 
-use crate::generate::element::Element;
+use crate::generate::{element::Element, error::BuildError, link::Link};
+
+/// How an image is fitted into the box, when both its width and height are set
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ImageFit {
+    /// Stretches the image to the box
+    #[default]
+    Fill,
+    /// Scales the image to fit into the box, keeping its aspect ratio. It is centered in the box.
+    Contain,
+    /// Scales the image to cover the whole box, keeping its aspect ratio. It is centered and the
+    /// overflow is clipped.
+    Cover,
+}
+
+/// The resolution the size of an image without a width or height is calculated with
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImageDpi {
+    Fixed(f32),
+    /// Read from the metadata of the image file. Images without it use 300 DPI.
+    Native,
+}
+
+impl Default for ImageDpi {
+    fn default() -> Self {
+        ImageDpi::Fixed(300.0)
+    }
+}
 
 pub struct Image {
     pub image: XObjectId,
     pub desired_width: Option<Mm>,
+    pub desired_height: Option<Mm>,
+    pub fit: ImageFit,
+    /// The image is scaled down to these, keeping its aspect ratio
+    pub max_width: Option<Mm>,
+    pub max_height: Option<Mm>,
+    pub dpi: ImageDpi,
+    pub link: Option<Link>,
 }
 
 impl Image {
@@ -12,8 +46,59 @@ impl Image {
         Image {
             image,
             desired_width,
+            desired_height: None,
+            fit: ImageFit::default(),
+            max_width: None,
+            max_height: None,
+            dpi: ImageDpi::default(),
+            link: None,
         }
     }
+
+    /// Without a height, the height is scaled to keep the aspect ratio
+    pub fn with_width(mut self, width: Mm) -> Self {
+        self.desired_width = Some(width);
+        self
+    }
+
+    /// Without a width, the width is scaled to keep the aspect ratio
+    pub fn with_height(mut self, height: Mm) -> Self {
+        self.desired_height = Some(height);
+        self
+    }
+
+    /// Sets the width and height of the box and how the image is fitted into it
+    pub fn with_box(mut self, width: Mm, height: Mm, fit: ImageFit) -> Self {
+        self.desired_width = Some(width);
+        self.desired_height = Some(height);
+        self.fit = fit;
+        self
+    }
+
+    pub fn with_fit(mut self, fit: ImageFit) -> Self {
+        self.fit = fit;
+        self
+    }
+
+    pub fn with_max_width(mut self, max_width: Mm) -> Self {
+        self.max_width = Some(max_width);
+        self
+    }
+
+    pub fn with_max_height(mut self, max_height: Mm) -> Self {
+        self.max_height = Some(max_height);
+        self
+    }
+
+    pub fn with_dpi(mut self, dpi: ImageDpi) -> Self {
+        self.dpi = dpi;
+        self
+    }
+
+    pub fn with_link(mut self, link: Link) -> Self {
+        self.link = Some(link);
+        self
+    }
 }
 
 impl Element for Image {
@@ -21,16 +106,25 @@ impl Element for Image {
         "Image"
     }
 
-    fn calculate_width<'a>(&self, builder: &super::element_builder::ElementBuilder<'a>) -> Pt {
-        builder.measure_image(self).0
+    fn calculate_width<'a>(
+        &self,
+        builder: &super::element_builder::ElementBuilder<'a>,
+    ) -> Result<Pt, BuildError> {
+        Ok(builder.measure_image(self)?.0)
     }
 
-    fn calculate_height<'a>(&self, builder: &super::element_builder::ElementBuilder<'a>) -> Pt {
-        builder.measure_image(self).1
+    fn calculate_height<'a>(
+        &self,
+        builder: &super::element_builder::ElementBuilder<'a>,
+    ) -> Result<Pt, BuildError> {
+        Ok(builder.measure_image(self)?.1)
     }
 
-    fn build<'a>(&self, builder: &mut super::element_builder::ElementBuilder<'a>) {
-        builder.push_image(self);
+    fn build<'a>(
+        &self,
+        builder: &mut super::element_builder::ElementBuilder<'a>,
+    ) -> Result<(), BuildError> {
+        builder.push_image(self)
     }
 }
 
@@ -2,6 +2,7 @@ use printpdf::{Mm, Pt};
 
 use crate::generate::{
     element::{Element, image::Image},
+    error::BuildError,
     padding::Padding,
 };
 
@@ -11,6 +12,12 @@ pub struct ImageFlex {
     space_y: Pt,
 }
 
+impl Default for ImageFlex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ImageFlex {
     pub fn new() -> Self {
         ImageFlex {
@@ -40,17 +47,23 @@ impl Element for ImageFlex {
         "Image Flex"
     }
 
-    fn calculate_width<'a>(&self, builder: &super::element_builder::ElementBuilder<'a>) -> Pt {
-        self.children
+    fn calculate_width<'a>(
+        &self,
+        builder: &super::element_builder::ElementBuilder<'a>,
+    ) -> Result<Pt, BuildError> {
+        Ok(self
+            .children
             .iter()
-            .fold(Pt(0.0), |width, elem| elem.calculate_width(builder) + width)
-            .min(builder.remaining_width_from_cursor())
+            .try_fold(Pt(0.0), |width, elem| {
+                builder.measure_child_width(elem).map(|w| w + width)
+            })?
+            .min(builder.remaining_width_from_cursor()))
     }
 
     fn calculate_height<'a>(
         &self,
         builder: &super::element_builder::ElementBuilder<'a>,
-    ) -> printpdf::Pt {
+    ) -> Result<Pt, BuildError> {
         // Create a group builder to calculate the height of children
         let group_builder = builder.generate_group_builder(&Padding::none(), None);
         group_builder.calculate_flex_height(
@@ -60,12 +73,15 @@ impl Element for ImageFlex {
         )
     }
 
-    fn build<'a>(&self, builder: &mut super::element_builder::ElementBuilder<'a>) {
+    fn build<'a>(
+        &self,
+        builder: &mut super::element_builder::ElementBuilder<'a>,
+    ) -> Result<(), BuildError> {
         builder.push_flex(
             self.children.iter().map(Box::new),
             self.space_x,
             self.space_y,
-        );
+        )
     }
 }
 // impl Element for ImageFlex {
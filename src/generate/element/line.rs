@@ -1,6 +1,6 @@
 use printpdf::Pt;
 
-use crate::generate::{element::Element, outline::LineStyle, padding::Padding};
+use crate::generate::{element::Element, error::BuildError, outline::LineStyle, padding::Padding};
 
 pub struct Line {
     outline: LineStyle,
@@ -18,14 +18,24 @@ impl Element for Line {
         "Line"
     }
 
-    fn calculate_width<'a>(&self, builder: &super::element_builder::ElementBuilder<'a>) -> Pt {
-        builder.remaining_width_from_cursor()
+    fn calculate_width<'a>(
+        &self,
+        builder: &super::element_builder::ElementBuilder<'a>,
+    ) -> Result<Pt, BuildError> {
+        Ok(builder.remaining_width_from_cursor())
     }
-    fn calculate_height<'a>(&self, _: &super::element_builder::ElementBuilder<'a>) -> Pt {
-        self.outline.thickness + self.padding.top.into_pt() + self.padding.bottom.into_pt()
+    fn calculate_height<'a>(
+        &self,
+        _: &super::element_builder::ElementBuilder<'a>,
+    ) -> Result<Pt, BuildError> {
+        Ok(self.outline.thickness + self.padding.top.into_pt() + self.padding.bottom.into_pt())
     }
 
-    fn build<'a>(&self, builder: &mut super::element_builder::ElementBuilder<'a>) {
+    fn build<'a>(
+        &self,
+        builder: &mut super::element_builder::ElementBuilder<'a>,
+    ) -> Result<(), BuildError> {
         builder.draw_line(&self.padding, &self.outline);
+        Ok(())
     }
 }
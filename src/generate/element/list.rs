@@ -0,0 +1,283 @@
+use printpdf::{Mm, Pt};
+
+use crate::generate::{
+    element::{
+        Element,
+        element_builder::{ColumnWidth, ElementBuilder, NumberFormat, TextListStyle},
+        paragraph::Paragraph,
+    },
+    error::BuildError,
+    font::Font,
+};
+
+/// Space between the marker and the content of an item
+const MARKER_GAP: Pt = Pt(4.0);
+
+/// A single entry of a list. It can contain any elements, including other lists for nesting.
+#[derive(Default)]
+pub struct ListItem {
+    pub elements: Vec<Box<dyn Element>>,
+}
+
+impl ListItem {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push<E>(&mut self, element: E)
+    where
+        E: Element + 'static,
+    {
+        self.elements.push(Box::new(element));
+    }
+
+    pub fn with<E>(mut self, element: E) -> Self
+    where
+        E: Element + 'static,
+    {
+        self.push(element);
+        self
+    }
+}
+
+/// Bulleted or numbered list.
+///
+/// The content of every item is indented, so wrapped lines stay aligned with the first line.
+/// Nested lists are pushed into an item and get indented further.
+pub struct List {
+    items: Vec<ListItem>,
+    style: TextListStyle,
+    /// Used for the markers and items added with [`List::push_text`]
+    font: Font,
+    indent: Mm,
+    item_spacing: Pt,
+    start: usize,
+}
+
+impl List {
+    pub fn new(style: TextListStyle, font: Font) -> Self {
+        Self {
+            items: Vec::new(),
+            style,
+            font,
+            indent: Mm(6.0),
+            item_spacing: Pt(2.0),
+            start: 1,
+        }
+    }
+
+    /// Width reserved for the markers, the content starts after it
+    pub fn with_indent(mut self, indent: Mm) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    pub fn with_item_spacing(mut self, spacing: Pt) -> Self {
+        self.item_spacing = spacing;
+        self
+    }
+
+    /// The number of the first item in numbered lists. Letters and roman numerals have no zero, so
+    /// a zero is always shown as a decimal number.
+    pub fn with_start(mut self, start: usize) -> Self {
+        self.start = start;
+        self
+    }
+
+    pub fn push_item(&mut self, item: ListItem) {
+        self.items.push(item);
+    }
+
+    pub fn with_item(mut self, item: ListItem) -> Self {
+        self.push_item(item);
+        self
+    }
+
+    /// Adds an item containing a single paragraph
+    pub fn push_text(&mut self, text: impl Into<String>) {
+        self.push_item(ListItem::new().with(Paragraph::new(text, self.font.clone())));
+    }
+
+    pub fn with_text(mut self, text: impl Into<String>) -> Self {
+        self.push_text(text);
+        self
+    }
+
+    /// The marker text of the item at `index`, `None` if the marker is not text
+    fn marker(&self, index: usize) -> Option<String> {
+        let number = self.start + index;
+
+        match self.style {
+            TextListStyle::Bulleted => Some("•".to_string()),
+            TextListStyle::Numbered(format) => Some(format!("{}.", format_number(number, format))),
+            TextListStyle::Checkbox => None,
+        }
+    }
+
+    fn item_height(
+        &self,
+        item: &ListItem,
+        content_builder: &ElementBuilder,
+    ) -> Result<Pt, BuildError> {
+        let content_height = item.elements.iter().try_fold(Pt(0.0), |total, element| {
+            content_builder
+                .measure_child_height(element.as_ref())
+                .map(|height| total + height)
+        })?;
+
+        Ok(content_height.max(self.font.font_size() + self.font.font_height_offset()))
+    }
+
+    fn build_marker(&self, builder: &mut ElementBuilder, index: usize) -> Result<(), BuildError> {
+        let marker_width = self.indent.into_pt() - MARKER_GAP;
+
+        match self.marker(index) {
+            Some(marker) => {
+                // Markers are aligned to the right, next to the content
+                let width = builder.measure_text(&marker, &self.font)?.0;
+                builder.cursor.x += (marker_width - width).max(Pt(0.0));
+                builder.push_paragraph(&marker, &self.font)?;
+            }
+            None => {
+                builder.cursor.x += (marker_width - self.font.font_size()).max(Pt(0.0));
+                builder.draw_rect(self.font.font_size());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Element for List {
+    fn display_name(&self) -> &str {
+        "List"
+    }
+
+    fn calculate_width<'a>(&self, builder: &ElementBuilder<'a>) -> Result<Pt, BuildError> {
+        Ok(builder.remaining_width_from_cursor())
+    }
+
+    fn calculate_height<'a>(&self, builder: &ElementBuilder<'a>) -> Result<Pt, BuildError> {
+        let (_, content_builder) = builder.generate_column_builder(ColumnWidth::Fixed(self.indent));
+
+        let spacing = self.item_spacing * self.items.len().saturating_sub(1) as f32;
+        self.items.iter().try_fold(spacing, |total, item| {
+            Ok(total + self.item_height(item, &content_builder)?)
+        })
+    }
+
+    fn build<'a>(&self, builder: &mut ElementBuilder<'a>) -> Result<(), BuildError> {
+        for (index, item) in self.items.iter().enumerate() {
+            if index > 0 {
+                builder.advance_cursor(self.item_spacing);
+            }
+
+            // Items are kept together, when they fit on a single page
+            let (_, content_builder) =
+                builder.generate_column_builder(ColumnWidth::Fixed(self.indent));
+            let height = self.item_height(item, &content_builder)?;
+            if height <= builder.page_style().inner_height().into_pt()
+                && builder.remaining_height_from_cursor() < height
+            {
+                builder.next_page();
+            }
+
+            let (mut marker_builder, mut content_builder) =
+                builder.generate_column_builder(ColumnWidth::Fixed(self.indent));
+
+            self.build_marker(&mut marker_builder, index)?;
+            for element in item.elements.iter() {
+                content_builder.build_child(element.as_ref())?;
+            }
+
+            let cursor = if content_builder.pages.len() > 1 {
+                content_builder.cursor.y
+            } else {
+                content_builder.cursor.y.min(marker_builder.cursor.y)
+            };
+
+            builder.merge(marker_builder);
+            builder.merge(content_builder);
+            builder.update_cursor(cursor);
+        }
+
+        Ok(())
+    }
+}
+
+fn format_number(number: usize, format: NumberFormat) -> String {
+    match format {
+        NumberFormat::Decimal => number.to_string(),
+        _ if number == 0 => number.to_string(),
+        NumberFormat::LowerAlpha => alpha(number),
+        NumberFormat::UpperAlpha => alpha(number).to_uppercase(),
+        NumberFormat::LowerRoman => roman(number).to_lowercase(),
+        NumberFormat::UpperRoman => roman(number),
+    }
+}
+
+/// a, b, ..., z, aa, ab, ...
+fn alpha(mut number: usize) -> String {
+    let mut letters = Vec::new();
+    while number > 0 {
+        number -= 1;
+        letters.push((b'a' + (number % 26) as u8) as char);
+        number /= 26;
+    }
+
+    letters.into_iter().rev().collect()
+}
+
+fn roman(mut number: usize) -> String {
+    const NUMERALS: [(usize, &str); 13] = [
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+
+    let mut result = String::new();
+    for (value, numeral) in NUMERALS {
+        while number >= value {
+            result.push_str(numeral);
+            number -= value;
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_numbers() {
+        assert_eq!(format_number(28, NumberFormat::LowerAlpha), "ab");
+        assert_eq!(format_number(26, NumberFormat::UpperAlpha), "Z");
+        assert_eq!(format_number(1994, NumberFormat::UpperRoman), "MCMXCIV");
+        assert_eq!(format_number(4, NumberFormat::LowerRoman), "iv");
+    }
+
+    #[test]
+    fn zero_falls_back_to_decimal() {
+        for format in [
+            NumberFormat::Decimal,
+            NumberFormat::LowerAlpha,
+            NumberFormat::UpperAlpha,
+            NumberFormat::LowerRoman,
+            NumberFormat::UpperRoman,
+        ] {
+            assert_eq!(format_number(0, format), "0");
+        }
+    }
+}
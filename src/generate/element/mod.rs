@@ -1,19 +1,27 @@
 use printpdf::{Mm, Op, Point, Pt};
 
 use crate::generate::element::element_builder::ElementBuilder;
+use crate::generate::error::BuildError;
 
+pub mod anchor;
 pub mod checkbox_group;
 pub mod column;
 pub mod cursor_offset;
 pub mod element_builder;
 pub mod empty;
 pub mod group;
+pub mod heading;
 pub mod image;
 pub mod image_flex;
 pub mod line;
+pub mod list;
 pub mod paragraph;
 pub mod rich_text;
+pub mod section;
 pub mod table;
+#[cfg(any(feature = "csv", feature = "serde"))]
+pub mod table_data;
+pub mod table_of_contents;
 
 pub struct BuildResult {
     pub ops: Vec<Op>,
@@ -24,7 +32,7 @@ pub struct BuildResult {
 pub trait Element {
     fn display_name(&self) -> &str;
 
-    fn calculate_width<'a>(&self, builder: &ElementBuilder<'a>) -> Pt;
-    fn calculate_height<'a>(&self, builder: &ElementBuilder<'a>) -> Pt;
-    fn build<'a>(&self, builder: &mut ElementBuilder<'a>);
+    fn calculate_width<'a>(&self, builder: &ElementBuilder<'a>) -> Result<Pt, BuildError>;
+    fn calculate_height<'a>(&self, builder: &ElementBuilder<'a>) -> Result<Pt, BuildError>;
+    fn build<'a>(&self, builder: &mut ElementBuilder<'a>) -> Result<(), BuildError>;
 }
@@ -2,13 +2,18 @@ use printpdf::Pt;
 
 use crate::generate::{
     element::{Element, element_builder::ElementBuilder},
+    error::BuildError,
     font::Font,
+    text_gen::TextAlign,
+    text_style::TextStyle,
 };
 
 pub struct Paragraph {
     text: String,
 
     font: Font,
+
+    align: TextAlign,
 }
 
 impl Paragraph {
@@ -16,8 +21,19 @@ impl Paragraph {
         Paragraph {
             text: text.into(),
             font,
+            align: TextAlign::Left,
         }
     }
+
+    pub fn with_align(mut self, align: TextAlign) -> Self {
+        self.align = align;
+        self
+    }
+
+    pub fn with_text_style(mut self, text_style: TextStyle) -> Self {
+        self.font = self.font.with_text_style(text_style);
+        self
+    }
 }
 
 impl Element for Paragraph {
@@ -25,15 +41,15 @@ impl Element for Paragraph {
         "Paragraph"
     }
 
-    fn calculate_width<'a>(&self, builder: &ElementBuilder<'a>) -> Pt {
-        builder.measure_text(self.text.as_str(), &self.font).0
+    fn calculate_width<'a>(&self, builder: &ElementBuilder<'a>) -> Result<Pt, BuildError> {
+        Ok(builder.measure_text(self.text.as_str(), &self.font)?.0)
     }
 
-    fn calculate_height<'a>(&self, builder: &ElementBuilder<'a>) -> Pt {
-        builder.measure_text(self.text.as_str(), &self.font).1
+    fn calculate_height<'a>(&self, builder: &ElementBuilder<'a>) -> Result<Pt, BuildError> {
+        Ok(builder.measure_text(self.text.as_str(), &self.font)?.1)
     }
 
-    fn build<'a>(&self, builder: &mut ElementBuilder<'a>) {
-        builder.push_paragraph(self.text.as_str(), &self.font);
+    fn build<'a>(&self, builder: &mut ElementBuilder<'a>) -> Result<(), BuildError> {
+        builder.push_paragraph_aligned(self.text.as_str(), &self.font, self.align)
     }
 }
@@ -1,12 +1,18 @@
+use std::collections::HashMap;
+
 use printpdf::Pt;
 
-use crate::generate::{element::Element, font::Font};
+use crate::generate::{
+    element::Element, error::BuildError, font::Font, link::Link, text_gen::TextAlign,
+};
 
 #[derive(Debug)]
 pub struct RichTextLinePart {
     pub text: String,
     pub font: Font,
     pub width: Pt,
+    /// Index of the part of the rich text, this text belongs to
+    pub part_index: usize,
 }
 
 #[derive(Default, Debug)]
@@ -17,14 +23,31 @@ pub struct RichTextLine {
 
 pub struct RichText {
     pub(crate) parts: Vec<(String, Font)>,
+    pub(crate) align: TextAlign,
+    /// Links of the parts by their index
+    pub(crate) links: HashMap<usize, Link>,
 }
 
 impl RichText {
     pub fn new(parts: impl Into<Vec<(String, Font)>>) -> Self {
         Self {
             parts: parts.into(),
+            align: TextAlign::Left,
+            links: HashMap::new(),
         }
     }
+
+    /// Appends a part, that is clickable
+    pub fn with_link_part(mut self, text: impl Into<String>, font: Font, link: Link) -> Self {
+        self.links.insert(self.parts.len(), link);
+        self.parts.push((text.into(), font));
+        self
+    }
+
+    pub fn with_align(mut self, align: TextAlign) -> Self {
+        self.align = align;
+        self
+    }
 }
 
 impl Element for RichText {
@@ -35,21 +58,24 @@ impl Element for RichText {
     fn calculate_width<'a>(
         &self,
         builder: &super::element_builder::ElementBuilder<'a>,
-    ) -> printpdf::Pt {
-        builder.remaining_width_from_cursor()
+    ) -> Result<Pt, BuildError> {
+        Ok(builder.remaining_width_from_cursor())
     }
 
     fn calculate_height<'a>(
         &self,
         builder: &super::element_builder::ElementBuilder<'a>,
-    ) -> printpdf::Pt {
-        let lines = builder.split_rich_text_into_lines(self);
+    ) -> Result<Pt, BuildError> {
+        let lines = builder.split_rich_text_into_lines(self)?;
         let height = lines.into_iter().map(|line| line.height.0).sum::<f32>();
 
-        Pt(height)
+        Ok(Pt(height))
     }
 
-    fn build<'a>(&self, builder: &mut super::element_builder::ElementBuilder<'a>) {
-        builder.push_rich_text(&self);
+    fn build<'a>(
+        &self,
+        builder: &mut super::element_builder::ElementBuilder<'a>,
+    ) -> Result<(), BuildError> {
+        builder.push_rich_text(self)
     }
 }
@@ -0,0 +1,102 @@
+use printpdf::Pt;
+
+use crate::generate::{
+    document::DocumentStyle,
+    document_builder::{DocumentFormat, DocumentOrientation},
+    element::{Element, element_builder::ElementBuilder},
+    error::BuildError,
+    padding::Padding,
+};
+
+/// Starts a new page with a different page setup.
+///
+/// The format, orientation and padding are used for all following pages, until the next section
+/// is started. A section can only be pushed to the document itself, building it inside a header,
+/// footer or another element like a group, column or table cell fails.
+pub struct Section {
+    format: DocumentFormat,
+    orientation: DocumentOrientation,
+    /// Keeps the padding of the previous section if not set
+    padding: Option<Padding>,
+}
+
+impl Section {
+    pub fn new(format: DocumentFormat, orientation: DocumentOrientation) -> Self {
+        Self {
+            format,
+            orientation,
+            padding: None,
+        }
+    }
+
+    pub fn with_padding(mut self, padding: Padding) -> Self {
+        self.padding = Some(padding);
+        self
+    }
+
+    fn style(&self, builder: &ElementBuilder) -> DocumentStyle {
+        let (width, height) = self.orientation.dimensions(self.format.dimensions());
+
+        DocumentStyle {
+            padding: self
+                .padding
+                .clone()
+                .unwrap_or_else(|| builder.page_style().padding.clone()),
+            width,
+            height,
+        }
+    }
+}
+
+impl Element for Section {
+    fn display_name(&self) -> &str {
+        "Section"
+    }
+
+    fn calculate_width<'a>(&self, _: &ElementBuilder<'a>) -> Result<Pt, BuildError> {
+        Ok(Pt(0.0))
+    }
+
+    fn calculate_height<'a>(&self, builder: &ElementBuilder<'a>) -> Result<Pt, BuildError> {
+        Ok(builder.remaining_height_from_cursor())
+    }
+
+    fn build<'a>(&self, builder: &mut ElementBuilder<'a>) -> Result<(), BuildError> {
+        let style = self.style(builder);
+        builder.start_section(style)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{
+        document_builder::DocumentBuilder, element::group::Group, error::BuildErrorKind,
+    };
+
+    fn landscape() -> Section {
+        Section::new(DocumentFormat::A4, DocumentOrientation::Landscape)
+    }
+
+    #[test]
+    fn starts_a_page_with_the_new_setup() {
+        let mut document = DocumentBuilder::new("test").build();
+        document.push(landscape());
+
+        let (pdf_document, _) = document.generate_document().unwrap();
+        assert_eq!(pdf_document.pages.len(), 1);
+        assert!(pdf_document.pages[0].media_box.width > pdf_document.pages[0].media_box.height);
+    }
+
+    #[test]
+    fn fails_inside_another_element() {
+        let mut group = Group::new();
+        group.push(landscape());
+        let mut document = DocumentBuilder::new("test").build();
+        document.push(group);
+
+        let error = document.generate_document().err().unwrap();
+        assert_eq!(error.kind, BuildErrorKind::NestedSection);
+        assert_eq!(error.path, vec!["Group", "Section"]);
+    }
+}
@@ -1,104 +1,1426 @@
-use printpdf::{Greyscale, Mm, Point, Pt};
+use std::{borrow::Cow, ops::Range};
+
+use printpdf::{Color, Greyscale, Mm, Point, Pt};
 use taffy::{
-    AvailableSpace, Display, NodeId, Overflow, Rect, Size, Style, TaffyTree,
-    prelude::{auto, length},
+    AvailableSpace, DetailedLayoutInfo, Display, Layout, LengthPercentage, Line,
+    MaxTrackSizingFunction, MinTrackSizingFunction, NodeId, Overflow, Rect, Size, Style, TaffyTree,
+    TrackSizingFunction,
+    prelude::{auto, length, line, minmax, span},
+};
+
+use crate::generate::{
+    diagnostic::{Diagnostic, DiagnosticKind},
+    element::{Element, element_builder::ElementBuilder},
+    error::{BuildError, BuildErrorKind},
+    font::Font,
+    outline::LineStyle,
+    padding::Padding,
+    text_gen::TextAlign,
 };
 
-use crate::generate::{element::Element, font::Font};
+/// Cells may be this much wider than the table, before they are reported as clipped
+const CLIP_TOLERANCE: Pt = Pt(0.5);
+/// Text may be this much wider than the content box of its cell, so rounding while shaping
+/// doesn't wrap it
+const TEXT_SLACK: Pt = Pt(3.0);
+/// Lines may end this much below a split, so that rounding doesn't move a line, which ends exactly
+/// at the split, to the next page
+const SPLIT_TOLERANCE: Pt = Pt(0.01);
+
+/// A cell of a [`Table`], that can span multiple columns and rows.
+pub struct TableCell {
+    body: CellBody,
+    colspan: usize,
+    rowspan: usize,
+    font: Option<Font>,
+    background: Option<Color>,
+    align: Option<TextAlign>,
+}
+
+impl TableCell {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self::with_body(CellBody::Text(text.into()))
+    }
+
+    /// A cell containing any element, e.g. rich text, an image or another table.
+    ///
+    /// The element gets the width of its column. Elements that always take the whole width, like
+    /// rich text, should be put into a column with a width, otherwise their column takes up the
+    /// whole table.
+    pub fn element<E>(element: E) -> Self
+    where
+        E: Element + 'static,
+    {
+        Self::with_body(CellBody::Element(Box::new(element)))
+    }
+
+    fn with_body(body: CellBody) -> Self {
+        Self {
+            body,
+            colspan: 1,
+            rowspan: 1,
+            font: None,
+            background: None,
+            align: None,
+        }
+    }
+
+    /// Overrides the font of the table for this cell. Ignored for elements.
+    pub fn with_font(mut self, font: Font) -> Self {
+        self.font = Some(font);
+        self
+    }
+
+    /// Overrides the fill of the row for this cell
+    pub fn with_background(mut self, color: Color) -> Self {
+        self.background = Some(color);
+        self
+    }
+
+    /// Overrides the alignment of the table for this cell. Ignored for elements.
+    pub fn with_align(mut self, align: TextAlign) -> Self {
+        self.align = Some(align);
+        self
+    }
+
+    /// Number of columns the cell covers, starting at its own column
+    pub fn with_colspan(mut self, colspan: usize) -> Self {
+        self.colspan = colspan.max(1);
+        self
+    }
+
+    /// Number of rows the cell covers, starting at its own row. Cells of the following rows skip
+    /// the covered columns.
+    pub fn with_rowspan(mut self, rowspan: usize) -> Self {
+        self.rowspan = rowspan.max(1);
+        self
+    }
+}
+
+/// What is drawn inside a cell
+enum CellBody {
+    Text(String),
+    Element(Box<dyn Element>),
+}
+
+impl From<String> for TableCell {
+    fn from(text: String) -> Self {
+        Self::new(text)
+    }
+}
+
+impl From<&str> for TableCell {
+    fn from(text: &str) -> Self {
+        Self::new(text)
+    }
+}
+
+/// Width of a column of a [`Table`]. Percentages and fractions are relative to the width of the
+/// table.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TableColumnWidth {
+    /// Sized by the content of the column, taking up the remaining space
+    Auto,
+    Fixed(Mm),
+    /// Part of the table width, from 0.0 to 1.0
+    Percent(f32),
+    /// Share of the space left after all other columns
+    Fraction(f32),
+    /// As narrow as possible, wrapping the text at every opportunity
+    MinContent,
+    /// As wide as the text without wrapping
+    MaxContent,
+    /// At least as wide as the first and at most as wide as the second width
+    MinMax(Box<TableColumnWidth>, Box<TableColumnWidth>),
+}
+
+impl TableColumnWidth {
+    pub fn minmax(min: TableColumnWidth, max: TableColumnWidth) -> Self {
+        Self::MinMax(Box::new(min), Box::new(max))
+    }
+
+    /// Fractions can't be used as a minimum and are treated as `Auto`
+    fn min_sizing(&self) -> MinTrackSizingFunction {
+        match self {
+            TableColumnWidth::Auto | TableColumnWidth::Fraction(_) => {
+                MinTrackSizingFunction::auto()
+            }
+            TableColumnWidth::Fixed(mm) => MinTrackSizingFunction::length(mm.into_pt().0),
+            TableColumnWidth::Percent(percent) => MinTrackSizingFunction::percent(*percent),
+            TableColumnWidth::MinContent => MinTrackSizingFunction::min_content(),
+            TableColumnWidth::MaxContent => MinTrackSizingFunction::max_content(),
+            TableColumnWidth::MinMax(min, _) => min.min_sizing(),
+        }
+    }
+
+    fn max_sizing(&self) -> MaxTrackSizingFunction {
+        match self {
+            TableColumnWidth::Auto => MaxTrackSizingFunction::auto(),
+            TableColumnWidth::Fixed(mm) => MaxTrackSizingFunction::length(mm.into_pt().0),
+            TableColumnWidth::Percent(percent) => MaxTrackSizingFunction::percent(*percent),
+            TableColumnWidth::Fraction(fraction) => MaxTrackSizingFunction::fr(*fraction),
+            TableColumnWidth::MinContent => MaxTrackSizingFunction::min_content(),
+            TableColumnWidth::MaxContent => MaxTrackSizingFunction::max_content(),
+            TableColumnWidth::MinMax(_, max) => max.max_sizing(),
+        }
+    }
+
+    fn track_sizing(&self) -> TrackSizingFunction {
+        minmax(self.min_sizing(), self.max_sizing())
+    }
+}
+
+/// Colors, borders and spacing of a [`Table`]. Fills and borders set to `None` are not drawn.
+#[derive(Clone, Debug)]
+pub struct TableStyle {
+    pub header_fill: Option<Color>,
+    /// Fill of the body rows, when they are not striped
+    pub body_fill: Option<Color>,
+    pub footer_fill: Option<Color>,
+    /// Alternating fills of the body rows, starting with the first color
+    pub stripes: Option<(Color, Color)>,
+    /// Drawn around the part of the table on every page
+    pub outer_border: Option<LineStyle>,
+    /// Drawn between rows
+    pub inner_horizontal_border: Option<LineStyle>,
+    /// Drawn between columns
+    pub inner_vertical_border: Option<LineStyle>,
+    pub cell_padding: Padding,
+    /// Alignment of the text in the cells
+    pub align: TextAlign,
+}
+
+impl Default for TableStyle {
+    fn default() -> Self {
+        Self {
+            header_fill: Some(Color::Greyscale(Greyscale::new(0.9, None))),
+            body_fill: None,
+            footer_fill: Some(Color::Greyscale(Greyscale::new(0.9, None))),
+            stripes: Some((
+                Color::Greyscale(Greyscale::new(0.95, None)),
+                Color::Greyscale(Greyscale::new(1.0, None)),
+            )),
+            outer_border: None,
+            inner_horizontal_border: None,
+            inner_vertical_border: None,
+            cell_padding: Padding::all(Pt(5.0).into()),
+            align: TextAlign::Left,
+        }
+    }
+}
+
+impl TableStyle {
+    pub fn with_header_fill(mut self, fill: Option<Color>) -> Self {
+        self.header_fill = fill;
+        self
+    }
+
+    pub fn with_body_fill(mut self, fill: Option<Color>) -> Self {
+        self.body_fill = fill;
+        self
+    }
+
+    pub fn with_footer_fill(mut self, fill: Option<Color>) -> Self {
+        self.footer_fill = fill;
+        self
+    }
+
+    pub fn with_stripes(mut self, stripes: Option<(Color, Color)>) -> Self {
+        self.stripes = stripes;
+        self
+    }
+
+    pub fn with_outer_border(mut self, line_style: Option<LineStyle>) -> Self {
+        self.outer_border = line_style;
+        self
+    }
+
+    pub fn with_inner_horizontal_border(mut self, line_style: Option<LineStyle>) -> Self {
+        self.inner_horizontal_border = line_style;
+        self
+    }
+
+    pub fn with_inner_vertical_border(mut self, line_style: Option<LineStyle>) -> Self {
+        self.inner_vertical_border = line_style;
+        self
+    }
+
+    /// Uses the line style for the outer and all inner borders
+    pub fn with_borders(self, line_style: LineStyle) -> Self {
+        self.with_outer_border(Some(line_style.clone()))
+            .with_inner_horizontal_border(Some(line_style.clone()))
+            .with_inner_vertical_border(Some(line_style))
+    }
+
+    pub fn with_cell_padding(mut self, padding: Padding) -> Self {
+        self.cell_padding = padding;
+        self
+    }
+
+    pub fn with_align(mut self, align: TextAlign) -> Self {
+        self.align = align;
+        self
+    }
+}
+
+/// Alignment of the cells of a column of a [`Table`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColumnAlign {
+    Left,
+    Center,
+    Right,
+    /// Aligns the decimal separators of the body and footer cells below each other. Header
+    /// cells are aligned right.
+    Decimal,
+}
+
+impl ColumnAlign {
+    fn text_align(self) -> TextAlign {
+        match self {
+            ColumnAlign::Left => TextAlign::Left,
+            ColumnAlign::Center => TextAlign::Center,
+            ColumnAlign::Right | ColumnAlign::Decimal => TextAlign::Right,
+        }
+    }
+}
+
+/// Formats the numbers in the body and footer cells of a column. Cells that are not a number
+/// are kept as they are.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NumericFormat {
+    decimals: Option<usize>,
+    thousands_separator: Option<char>,
+    decimal_separator: char,
+    prefix: String,
+    suffix: String,
+}
+
+impl Default for NumericFormat {
+    fn default() -> Self {
+        Self {
+            decimals: None,
+            thousands_separator: None,
+            decimal_separator: '.',
+            prefix: String::new(),
+            suffix: String::new(),
+        }
+    }
+}
+
+impl NumericFormat {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rounds half away from zero to a fixed number of decimals. Without it, the decimals of the
+    /// cell are kept.
+    pub fn with_decimals(mut self, decimals: usize) -> Self {
+        self.decimals = Some(decimals);
+        self
+    }
+
+    pub fn with_thousands_separator(mut self, separator: char) -> Self {
+        self.thousands_separator = Some(separator);
+        self
+    }
+
+    pub fn with_decimal_separator(mut self, separator: char) -> Self {
+        self.decimal_separator = separator;
+        self
+    }
+
+    /// Put in front of the number, e.g. a currency symbol like "$"
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Put after the number, e.g. a currency like " EUR"
+    pub fn with_suffix(mut self, suffix: impl Into<String>) -> Self {
+        self.suffix = suffix.into();
+        self
+    }
+
+    /// Returns `None`, if the text is not a number
+    fn format(&self, text: &str) -> Option<String> {
+        let text = text.trim();
+        let value = text.parse::<f64>().ok().filter(|value| value.is_finite())?;
+
+        // Rounded on the written digits, so that the binary value of the float doesn't matter
+        let digits = plain_digits(text.trim_start_matches(['+', '-']))?;
+        let digits = match self.decimals {
+            Some(decimals) => round_digits(&digits, decimals),
+            None => digits,
+        };
+        let (integer, fraction) = match digits.split_once('.') {
+            Some((integer, fraction)) => (integer, Some(fraction).filter(|f| !f.is_empty())),
+            None => (digits.as_str(), None),
+        };
+        // ".5" is written as "0.5"
+        let integer = if integer.is_empty() { "0" } else { integer };
+
+        let mut number = String::new();
+        if value < 0.0 && digits.chars().any(|c| c.is_ascii_digit() && c != '0') {
+            number.push('-');
+        }
+        number.push_str(&self.prefix);
+        for (index, digit) in integer.chars().enumerate() {
+            if let Some(separator) = self.thousands_separator
+                && index > 0
+                && (integer.len() - index) % 3 == 0
+            {
+                number.push(separator);
+            }
+            number.push(digit);
+        }
+        if let Some(fraction) = fraction {
+            number.push(self.decimal_separator);
+            number.push_str(fraction);
+        }
+        number.push_str(&self.suffix);
+
+        Some(number)
+    }
+}
+
+/// Writes a number without sign in exponent notation out. Plain numbers are kept as they are,
+/// including trailing zeros.
+fn plain_digits(number: &str) -> Option<String> {
+    let Some((mantissa, exponent)) = number.split_once(['e', 'E']) else {
+        return Some(number.to_string());
+    };
+    let exponent = exponent.parse::<isize>().ok()?;
+
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let all_digits = format!("{integer}{fraction}");
+    let digits = all_digits.trim_start_matches('0');
+    if digits.is_empty() {
+        return Some("0".to_string());
+    }
+    // Position of the decimal point in the digits without leading zeros
+    let point = (integer.len() + digits.len()) as isize - all_digits.len() as isize + exponent;
+
+    let plain = if point <= 0 {
+        format!("0.{}{digits}", "0".repeat(point.unsigned_abs()))
+    } else if point as usize >= digits.len() {
+        format!("{digits}{}", "0".repeat(point as usize - digits.len()))
+    } else {
+        let (integer, fraction) = digits.split_at(point as usize);
+        format!("{integer}.{fraction}")
+    };
+
+    // Like other formatting of numbers, without trailing zeros
+    let plain = match plain.split_once('.') {
+        Some((integer, fraction)) => match fraction.trim_end_matches('0') {
+            "" if integer.is_empty() => "0".to_string(),
+            "" => integer.to_string(),
+            fraction => format!("{integer}.{fraction}"),
+        },
+        None => plain,
+    };
+    Some(plain)
+}
+
+/// Rounds the digits of a plain number without sign half away from zero
+fn round_digits(digits: &str, decimals: usize) -> String {
+    let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    let integer = if integer.is_empty() { "0" } else { integer };
+
+    let mut kept = format!("{integer}{fraction:0<decimals$}")
+        .chars()
+        .take(integer.len() + decimals)
+        .collect::<Vec<_>>();
+    if fraction
+        .chars()
+        .nth(decimals)
+        .is_some_and(|digit| digit >= '5')
+    {
+        let mut carry = true;
+        for digit in kept.iter_mut().rev() {
+            if *digit == '9' {
+                *digit = '0';
+            } else {
+                *digit = (*digit as u8 + 1) as char;
+                carry = false;
+                break;
+            }
+        }
+        if carry {
+            kept.insert(0, '1');
+        }
+    }
+
+    let point = kept.len() - decimals;
+    let integer = kept[..point].iter().collect::<String>();
+    if decimals == 0 {
+        integer
+    } else {
+        format!("{integer}.{}", kept[point..].iter().collect::<String>())
+    }
+}
+
+/// The part of a number from the decimal separator on, or the text after the last digit, if it
+/// has no decimals
+fn decimal_part(text: &str, separator: char) -> &str {
+    match text.rfind(separator) {
+        Some(index) => &text[index..],
+        None => &text[text.trim_end_matches(|c: char| !c.is_ascii_digit()).len()..],
+    }
+}
+
+pub struct Table {
+    font: Font,
+    num_cols: usize,
+    style: TableStyle,
+    column_widths: Vec<TableColumnWidth>,
+    column_aligns: Vec<Option<ColumnAlign>>,
+    column_formats: Vec<Option<NumericFormat>>,
+    header: Vec<Vec<TableCell>>,
+    header_font: Option<Font>,
+    content: Vec<Vec<TableCell>>,
+    /// Whether the body row with the same index may be split across pages
+    splittable: Vec<bool>,
+    footer: Vec<Vec<TableCell>>,
+    page_subtotal: Option<Box<PageSubtotal>>,
+    caption_before_break: Option<String>,
+    caption_after_break: Option<String>,
+}
+
+/// Creates the cells of a subtotal row from the indices of the body rows on a page
+type PageSubtotal = dyn Fn(Range<usize>) -> Vec<TableCell>;
+
+/// How a cell can be split at a position
+struct CellSplit {
+    cell_top: Pt,
+    /// The lowest position above, where the cell can be split, if it can't be split at the
+    /// position itself
+    position: Option<Pt>,
+    /// Whether there is content of the cell below the position
+    content_below: bool,
+}
+
+/// Position of the table on the current page, while it is built
+struct TablePage {
+    /// Top of the part of the table on the page
+    top: Pt,
+    /// The first row, that started on the page
+    first_row: usize,
+    /// Whether the table continued from a previous page
+    continued: bool,
+}
+
+impl Table {
+    pub fn new(num_cols: usize, font: Font) -> Self {
+        Self {
+            num_cols,
+            style: TableStyle::default(),
+            column_widths: vec![TableColumnWidth::Auto; num_cols],
+            column_aligns: vec![None; num_cols],
+            column_formats: vec![None; num_cols],
+            header: Vec::new(),
+            header_font: None,
+            content: Vec::default(),
+            splittable: Vec::new(),
+            footer: Vec::new(),
+            page_subtotal: None,
+            caption_before_break: None,
+            caption_after_break: None,
+            font,
+        }
+    }
+
+    pub fn with_style(mut self, style: TableStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Sets the widths of the columns from left to right. Columns without a width are sized
+    /// automatically, widths for columns the table doesn't have are ignored.
+    pub fn with_column_widths(
+        mut self,
+        widths: impl IntoIterator<Item = TableColumnWidth>,
+    ) -> Self {
+        let mut widths = widths.into_iter();
+        for width in self.column_widths.iter_mut() {
+            *width = widths.next().unwrap_or(TableColumnWidth::Auto);
+        }
+        self
+    }
+
+    /// Sets the width of a single column. Ignored, if the table doesn't have the column.
+    pub fn set_column_width(&mut self, col: usize, width: TableColumnWidth) {
+        if let Some(column_width) = self.column_widths.get_mut(col) {
+            *column_width = width;
+        }
+    }
+
+    /// Sets the alignments of the columns from left to right. They override the alignment of the
+    /// style, but not the alignment of a cell.
+    pub fn with_column_aligns(mut self, aligns: impl IntoIterator<Item = ColumnAlign>) -> Self {
+        let mut aligns = aligns.into_iter();
+        for align in self.column_aligns.iter_mut() {
+            *align = aligns.next();
+        }
+        self
+    }
+
+    /// Sets the alignment of a single column. Ignored, if the table doesn't have the column.
+    pub fn set_column_align(&mut self, col: usize, align: ColumnAlign) {
+        if let Some(column_align) = self.column_aligns.get_mut(col) {
+            *column_align = Some(align);
+        }
+    }
+
+    /// Formats the numbers in the column. Ignored, if the table doesn't have the column.
+    pub fn with_column_format(mut self, col: usize, format: NumericFormat) -> Self {
+        self.set_column_format(col, format);
+        self
+    }
+
+    pub fn set_column_format(&mut self, col: usize, format: NumericFormat) {
+        if let Some(column_format) = self.column_formats.get_mut(col) {
+            *column_format = Some(format);
+        }
+    }
+
+    /// Sets a single header row, that is repeated on every page. Uses the font of the table, if
+    /// no font is given.
+    pub fn set_header(
+        &mut self,
+        header: impl IntoIterator<Item = impl Into<TableCell>>,
+        font: Option<Font>,
+    ) {
+        self.header = vec![header.into_iter().map(Into::into).collect()];
+        self.header_font = font;
+    }
+
+    /// Adds another row to the header, e.g. for merged header cells above the column headers
+    pub fn add_header_row(&mut self, row: impl IntoIterator<Item = impl Into<TableCell>>) {
+        self.header.push(row.into_iter().map(Into::into).collect());
+    }
+
+    /// Adds a row. Together with the cells spanning from the rows above, it has to fill all
+    /// columns.
+    ///
+    /// Rows that don't fit on the page are split between two lines of text and continue on the
+    /// next page.
+    pub fn add_row(&mut self, row: impl IntoIterator<Item = impl Into<TableCell>>) {
+        self.content.push(row.into_iter().map(Into::into).collect());
+        self.splittable.push(true);
+    }
+
+    /// Adds a row, that is never split across pages. It starts on a new page, if it doesn't fit
+    /// on the current one.
+    pub fn add_unsplittable_row(&mut self, row: impl IntoIterator<Item = impl Into<TableCell>>) {
+        self.add_row(row);
+        *self.splittable.last_mut().expect("Row was just added") = false;
+    }
+
+    /// Adds a row, that is shown once after the last row, e.g. for the totals of the columns
+    pub fn add_footer_row(&mut self, row: impl IntoIterator<Item = impl Into<TableCell>>) {
+        self.footer.push(row.into_iter().map(Into::into).collect());
+    }
+
+    /// Adds a subtotal row at the bottom of every page, when the table spans multiple pages.
+    ///
+    /// The function gets the indices of the rows, that start on the page, and returns the cells
+    /// of the subtotal row. Pages without a new row don't get a subtotal.
+    pub fn with_page_subtotal<F>(mut self, subtotal: F) -> Self
+    where
+        F: Fn(Range<usize>) -> Vec<TableCell> + 'static,
+    {
+        self.page_subtotal = Some(Box::new(subtotal));
+        self
+    }
+
+    /// Shown right aligned below the table on every page, the table continues after, e.g.
+    /// "continued on next page"
+    pub fn with_caption_before_break(mut self, caption: impl Into<String>) -> Self {
+        self.caption_before_break = Some(caption.into());
+        self
+    }
+
+    /// Shown above the table on every page, the table continues on, e.g. "continued"
+    pub fn with_caption_after_break(mut self, caption: impl Into<String>) -> Self {
+        self.caption_after_break = Some(caption.into());
+        self
+    }
+
+    /// Formats the cell and resolves its font and alignment. The decimal inset is set
+    /// afterwards by [`Table::align_decimals`].
+    fn cell_content<'t>(
+        &self,
+        cell: &'t TableCell,
+        font: &Font,
+        placement: &Placement,
+        header_rows: usize,
+    ) -> CellContent<'t> {
+        let header = placement.row < header_rows;
+        let value = match &cell.body {
+            CellBody::Text(text) => {
+                let formatted = self.column_formats[placement.col]
+                    .as_ref()
+                    .filter(|_| !header)
+                    .and_then(|format| format.format(text));
+                CellValue::Text(formatted.map_or(Cow::Borrowed(text.as_str()), Cow::Owned))
+            }
+            CellBody::Element(element) => CellValue::Element(element.as_ref()),
+        };
+        let align = cell
+            .align
+            .or(self.column_aligns[placement.col].map(ColumnAlign::text_align))
+            .unwrap_or(self.style.align);
+
+        CellContent {
+            value,
+            font: cell.font.as_ref().unwrap_or(font).clone(),
+            align,
+            inset: Pt(0.0),
+            background: cell.background.clone(),
+        }
+    }
+
+    /// Moves the text of the cells in decimal aligned columns to the left, so their decimal
+    /// separators line up. Only cells of a single column, that are not in the header and have no
+    /// own alignment, are aligned.
+    fn align_decimals(
+        &self,
+        builder: &ElementBuilder,
+        contents: &mut [(&TableCell, CellContent, Placement)],
+    ) -> Result<(), BuildError> {
+        let header_rows = self.header.len();
+        let separator = |col: usize| {
+            self.column_formats[col]
+                .as_ref()
+                .map_or('.', |format| format.decimal_separator)
+        };
+
+        // Width of the decimal part of every aligned cell
+        let mut decimal_widths = Vec::new();
+        for (index, (cell, content, placement)) in contents.iter().enumerate() {
+            let CellValue::Text(text) = &content.value else {
+                continue;
+            };
+            if self.column_aligns[placement.col] != Some(ColumnAlign::Decimal)
+                || placement.row < header_rows
+                || placement.colspan > 1
+                || cell.align.is_some()
+            {
+                continue;
+            }
+
+            let part = decimal_part(text, separator(placement.col));
+            let width = builder.measure_text_manuel(part, &content.font, None)?.0;
+            decimal_widths.push((index, placement.col, width));
+        }
+
+        let mut max_widths = vec![Pt(0.0); self.num_cols];
+        for (_, col, width) in decimal_widths.iter() {
+            max_widths[*col] = max_widths[*col].max(*width);
+        }
+        for (index, col, width) in decimal_widths {
+            contents[index].1.inset = max_widths[col] - width;
+        }
+
+        Ok(())
+    }
+
+    fn header_font(&self) -> &Font {
+        self.header_font.as_ref().unwrap_or(&self.font)
+    }
+
+    fn build_headers(
+        &self,
+        builder: &mut ElementBuilder,
+        built: &BuiltTable,
+        draw: bool,
+    ) -> Result<(), BuildError> {
+        if built.header_rows > 0 {
+            let height = built.rows_height(0..built.header_rows);
+            self.build_rows(builder, built, 0..built.header_rows, Pt(0.0)..height, draw)?;
+        }
+
+        Ok(())
+    }
+
+    /// Builds the part `slice` of the rows at the cursor and moves the cursor below it. The slice
+    /// is relative to the top of the first row. Only moves the cursor, if `draw` is false.
+    fn build_rows(
+        &self,
+        builder: &mut ElementBuilder,
+        built: &BuiltTable,
+        rows: Range<usize>,
+        slice: Range<Pt>,
+        draw: bool,
+    ) -> Result<(), BuildError> {
+        let height = slice.end - slice.start;
+
+        if draw {
+            let rows_top = built.row_top(rows.start);
+            let top = rows_top + slice.start;
+            let origin = builder.cursor;
+            builder.begin_clip(built.width, height);
+
+            for cell in built.cells.iter().filter(|cell| rows.contains(&cell.row)) {
+                let layout = built.taffy.layout(cell.node).map_err(layout_error)?;
+                let cell_top = Pt(layout.location.y) - rows_top;
+                if cell_top >= slice.end || cell_top + Pt(layout.size.height) <= slice.start {
+                    continue;
+                }
+
+                builder.cursor = Point {
+                    x: origin.x + Pt(layout.location.x),
+                    y: origin.y - (Pt(layout.location.y) - top),
+                };
+                self.build_cell(builder, built, cell, cell_top < slice.start)?;
+            }
+
+            builder.cursor = origin;
+            self.draw_inner_borders(builder, built, rows.clone(), top)?;
+            builder.end_clip();
+        }
+
+        builder.advance_cursor(height);
+        builder.reset_cursor_x();
+        Ok(())
+    }
+
+    /// Builds the background and the content of the cell at the cursor. The content is clipped to
+    /// the cell.
+    ///
+    /// `continued` is set, when the cell was split and its beginning is on a previous page.
+    fn build_cell(
+        &self,
+        builder: &mut ElementBuilder,
+        built: &BuiltTable,
+        cell: &BuiltCell,
+        continued: bool,
+    ) -> Result<(), BuildError> {
+        let layout = built.taffy.layout(cell.node).map_err(layout_error)?;
+        let Some(content) = built.taffy.get_node_context(cell.node) else {
+            return Ok(());
+        };
+
+        let fill = content
+            .background
+            .clone()
+            .or_else(|| self.row_fill(built, cell.row));
+        if let Some(fill) = fill {
+            builder.fill_rect_dont_change_cursor(
+                Pt(layout.size.width),
+                Pt(layout.size.height),
+                fill,
+            );
+        }
+
+        builder.begin_clip(Pt(layout.size.width), Pt(layout.size.height));
+        let result = match &content.value {
+            CellValue::Text(text) => {
+                self.build_cell_text(builder, built, cell, content, text, continued)
+            }
+            CellValue::Element(element) => {
+                self.build_cell_element(builder, built, cell, *element, continued)
+            }
+        };
+        builder.end_clip();
+
+        result
+    }
+
+    fn build_cell_text(
+        &self,
+        builder: &mut ElementBuilder,
+        built: &BuiltTable,
+        cell: &BuiltCell,
+        content: &CellContent,
+        text: &str,
+        continued: bool,
+    ) -> Result<(), BuildError> {
+        let layout = built.taffy.layout(cell.node).map_err(layout_error)?;
+        let max_width = content.text_width(layout);
+        let min_width = builder.measure_text_min_content(text, &content.font)?;
+        // Cells that are too wide for the table are cut off at the edge of the page
+        let outside = (Pt(layout.size.width) - builder.remaining_width_from_cursor())
+            .min(Pt(layout.size.width));
+        if !continued && (min_width > max_width || outside > CLIP_TOLERANCE) {
+            let position = built.position(cell);
+            let reason = if min_width > max_width {
+                format!(
+                    "needs {:.1}pt, but the cell is only {:.1}pt wide",
+                    min_width.0, max_width.0
+                )
+            } else {
+                format!("extends {:.1}pt past the table", outside.0)
+            };
+            builder.push_diagnostic(Diagnostic::new(
+                DiagnosticKind::ClippedTableCell,
+                format!("\"{text}\" in {position} {reason}"),
+            ));
+        }
+
+        // The slack is only added on the right, so aligned text keeps the padding
+        let slack = match content.align {
+            TextAlign::Left | TextAlign::Justify => Pt(0.0),
+            TextAlign::Center => TEXT_SLACK / 2.0,
+            TextAlign::Right => TEXT_SLACK,
+        };
+        builder.push_text_aligned_dont_change_cursor(
+            text,
+            &content.font,
+            Point {
+                x: Pt(layout.padding.left) - slack,
+                y: Pt(layout.padding.top),
+            },
+            Some(max_width),
+            content.align,
+        )
+    }
+
+    fn build_cell_element(
+        &self,
+        builder: &mut ElementBuilder,
+        built: &BuiltTable,
+        cell: &BuiltCell,
+        element: &dyn Element,
+        continued: bool,
+    ) -> Result<(), BuildError> {
+        let layout = built.taffy.layout(cell.node).map_err(layout_error)?;
+        let mut cell_builder = builder.generate_cell_builder(
+            Point {
+                x: Pt(layout.padding.left),
+                y: Pt(layout.padding.top),
+            },
+            Pt(layout.content_box_width()),
+        );
+        let top = cell_builder.cursor.y;
+        cell_builder.build_child(element)?;
+
+        let height = top - cell_builder.cursor.y;
+        let max_height = Pt(layout.content_box_height());
+        if !continued && height > max_height + CLIP_TOLERANCE {
+            builder.push_diagnostic(Diagnostic::new(
+                DiagnosticKind::ClippedTableCell,
+                format!(
+                    "{} in {} needs {:.1}pt, but the cell is only {:.1}pt high",
+                    element.display_name(),
+                    built.position(cell),
+                    height.0,
+                    max_height.0
+                ),
+            ));
+        }
+
+        builder.merge(cell_builder);
+        Ok(())
+    }
+
+    /// Whether all rows may be split. Header rows are never split.
+    fn is_splittable(&self, built: &BuiltTable, rows: Range<usize>) -> bool {
+        rows.into_iter()
+            .all(|row| row >= built.header_rows && self.splittable[row - built.header_rows])
+    }
+
+    /// Fill of the row, before it is overridden by its cells
+    fn row_fill(&self, built: &BuiltTable, row: usize) -> Option<Color> {
+        if row < built.header_rows {
+            return self.style.header_fill.clone();
+        }
+        if row >= built.footer_start() {
+            return self.style.footer_fill.clone();
+        }
+
+        match &self.style.stripes {
+            Some((even, odd)) => match (row - built.header_rows) % 2 {
+                0 => Some(even.clone()),
+                _ => Some(odd.clone()),
+            },
+            None => self.style.body_fill.clone(),
+        }
+    }
+
+    /// Draws the borders between the cells of the rows. `top` is the position in the table, that
+    /// is at the cursor.
+    fn draw_inner_borders(
+        &self,
+        builder: &mut ElementBuilder,
+        built: &BuiltTable,
+        rows: Range<usize>,
+        top: Pt,
+    ) -> Result<(), BuildError> {
+        let origin = builder.cursor;
+
+        for cell in built.cells.iter().filter(|cell| rows.contains(&cell.row)) {
+            let layout = built.taffy.layout(cell.node).map_err(layout_error)?;
+            let x = origin.x + Pt(layout.location.x);
+            let y = origin.y - (Pt(layout.location.y) - top);
+
+            if let Some(line_style) = &self.style.inner_horizontal_border
+                && cell.row > rows.start
+            {
+                let to = Point {
+                    x: x + Pt(layout.size.width),
+                    y,
+                };
+                builder.stroke_line(Point { x, y }, to, line_style);
+            }
+            if let Some(line_style) = &self.style.inner_vertical_border
+                && cell.col > 0
+            {
+                let to = Point {
+                    x,
+                    y: y - Pt(layout.size.height),
+                };
+                builder.stroke_line(Point { x, y }, to, line_style);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Draws the horizontal border between the rows above and below the cursor
+    fn draw_row_separator(&self, builder: &mut ElementBuilder, built: &BuiltTable) {
+        if let Some(line_style) = &self.style.inner_horizontal_border {
+            let from = builder.cursor;
+            let to = Point {
+                x: from.x + built.width,
+                y: from.y,
+            };
+            builder.stroke_line(from, to, line_style);
+        }
+    }
+
+    /// Draws the outer border around the part of the table from `top` to the cursor
+    fn draw_outer_border(&self, builder: &mut ElementBuilder, built: &BuiltTable, top: Pt) {
+        let Some(line_style) = &self.style.outer_border else {
+            return;
+        };
+        let bottom = builder.cursor.y;
+        if bottom >= top {
+            return;
+        }
+
+        let left = builder.cursor.x;
+        let right = left + built.width;
+        let corners = [
+            Point { x: left, y: top },
+            Point { x: right, y: top },
+            Point {
+                x: right,
+                y: bottom,
+            },
+            Point { x: left, y: bottom },
+        ];
+        for (index, from) in corners.iter().enumerate() {
+            builder.stroke_line(*from, corners[(index + 1) % corners.len()], line_style);
+        }
+    }
+
+    /// Finds the lowest position between `start` and `max`, where the rows can be split without
+    /// cutting a line of text in half. Elements in cells are never split, the rows are split
+    /// above them instead. Positions are relative to the top of the first row.
+    fn split_position(
+        &self,
+        builder: &ElementBuilder,
+        built: &BuiltTable,
+        rows: Range<usize>,
+        start: Pt,
+        max: Pt,
+    ) -> Result<Pt, BuildError> {
+        let mut end = max;
+
+        // Moving the split up can cut lines of cells, that were checked before
+        loop {
+            let mut changed = false;
+
+            for split in self.cell_splits(builder, built, rows.clone(), start, end)? {
+                if let Some(split) = split.position
+                    && split < end
+                {
+                    end = split;
+                    changed = true;
+                }
+            }
+
+            if !changed {
+                break;
+            }
+        }
+
+        // Only padding would be left of the cells, so they are moved to the next page instead
+        let splits = self.cell_splits(builder, built, rows, start, end)?;
+        if !splits.is_empty() && splits.iter().all(|split| !split.content_below) {
+            let top = splits
+                .iter()
+                .fold(end, |top, split| top.min(split.cell_top));
+            return Ok(top.max(start));
+        }
+
+        Ok(end)
+    }
+
+    /// Where the cells, that are cut by `end`, could be split
+    fn cell_splits(
+        &self,
+        builder: &ElementBuilder,
+        built: &BuiltTable,
+        rows: Range<usize>,
+        start: Pt,
+        end: Pt,
+    ) -> Result<Vec<CellSplit>, BuildError> {
+        let rows_top = built.row_top(rows.start);
+        let mut splits = Vec::new();
+
+        for cell in built.cells.iter().filter(|cell| rows.contains(&cell.row)) {
+            let layout = built.taffy.layout(cell.node).map_err(layout_error)?;
+            let Some(content) = built.taffy.get_node_context(cell.node) else {
+                continue;
+            };
+            let cell_top = Pt(layout.location.y) - rows_top;
+            if cell_top >= end || cell_top + Pt(layout.size.height) <= end {
+                continue;
+            }
+
+            let (position, content_below) = match &content.value {
+                CellValue::Text(text) => {
+                    let text_top = cell_top + Pt(layout.padding.top);
+                    let width = content.text_width(layout);
+                    let fitting = if end > text_top {
+                        builder.fitting_text_height(
+                            text,
+                            &content.font,
+                            width,
+                            end - text_top + SPLIT_TOLERANCE,
+                        )?
+                    } else {
+                        Pt(0.0)
+                    };
+                    let (_, text_height) =
+                        builder.measure_text_manuel(text, &content.font, Some(width))?;
+
+                    let line_end = text_top + fitting;
+                    if fitting >= text_height {
+                        (None, false)
+                    } else if fitting > Pt(0.0) && line_end > start {
+                        (Some(line_end), true)
+                    } else if cell_top >= start {
+                        // The first line doesn't fit, so the padding is kept with it
+                        (Some(cell_top), true)
+                    } else {
+                        (Some(line_end.max(start)), true)
+                    }
+                }
+                // Elements that started on a previous page are clipped
+                CellValue::Element(_) if cell_top <= start => (None, true),
+                CellValue::Element(_) => (Some(cell_top), true),
+            };
+
+            splits.push(CellSplit {
+                cell_top,
+                position,
+                content_below,
+            });
+        }
 
-pub struct Table {
-    font: Font,
-    num_cols: usize,
-    header: Option<(Vec<String>, Font)>,
-    content: Vec<Vec<String>>,
-}
+        Ok(splits)
+    }
 
-impl Table {
-    pub fn new(num_cols: usize, font: Font) -> Self {
-        Self {
-            num_cols,
-            header: None,
-            content: Vec::default(),
-            font,
+    /// Builds a table with the same columns, that only contains the subtotal row for the rows
+    fn subtotal_table(&self, built: &BuiltTable, rows: Range<usize>) -> Option<Table> {
+        let subtotal = self.page_subtotal.as_ref()?;
+        if rows.is_empty() {
+            return None;
         }
-    }
 
-    pub fn set_header(&mut self, header: Vec<String>, font: Option<Font>) {
-        assert_eq!(header.len(), self.num_cols);
-        self.header = Some((header, font.unwrap_or(self.font.clone())));
+        let body_rows = rows.start - built.header_rows..rows.end - built.header_rows;
+        let mut table = Table::new(self.num_cols, self.font.clone())
+            .with_style(self.style.clone())
+            .with_column_widths(
+                built
+                    .column_widths
+                    .iter()
+                    .map(|width| TableColumnWidth::Fixed((*width).into())),
+            );
+        table.column_aligns = self.column_aligns.clone();
+        table.column_formats = self.column_formats.clone();
+        table.add_footer_row(subtotal(body_rows));
+
+        Some(table)
     }
 
-    pub fn add_row(&mut self, row: impl IntoIterator<Item = String>) {
-        let vec = row.into_iter().collect::<Vec<_>>();
+    /// Height of the subtotal row for the rows
+    fn subtotal_height(
+        &self,
+        builder: &ElementBuilder,
+        built: &BuiltTable,
+        rows: Range<usize>,
+    ) -> Result<Pt, BuildError> {
+        let Some(table) = self.subtotal_table(built, rows) else {
+            return Ok(Pt(0.0));
+        };
 
-        assert_eq!(vec.len(), self.num_cols);
-        self.content.push(vec);
+        let subtotal = BuiltTable::build(&table, builder)?;
+        Ok(subtotal.rows_height(subtotal.footer_start()..subtotal.row_heights.len()))
     }
 
-    fn build_headers<'a>(
+    /// Builds the subtotal row for the rows and returns its height
+    fn build_subtotal(
         &self,
-        builder: &mut super::element_builder::ElementBuilder<'a>,
+        builder: &mut ElementBuilder,
         built: &BuiltTable,
-    ) {
-        if let Some(header_cells) = built.header_cells() {
-            for (i, node) in header_cells.iter().enumerate() {
-                self.build_cell(builder, built, i, *node, true);
-            }
+        rows: Range<usize>,
+        draw: bool,
+    ) -> Result<Pt, BuildError> {
+        let Some(table) = self.subtotal_table(built, rows) else {
+            return Ok(Pt(0.0));
+        };
+
+        let subtotal = BuiltTable::build(&table, builder)?;
+        let footer = subtotal.footer_start()..subtotal.row_heights.len();
+        let height = subtotal.rows_height(footer.clone());
+        if draw {
+            self.draw_row_separator(builder, built);
         }
+        table.build_rows(builder, &subtotal, footer, Pt(0.0)..height, draw)?;
+
+        Ok(height)
     }
 
-    fn build_cell<'a>(
+    fn caption_height(
         &self,
-        builder: &mut super::element_builder::ElementBuilder<'a>,
+        builder: &ElementBuilder,
         built: &BuiltTable,
-        index: usize,
-        node: NodeId,
-        is_header: bool,
-    ) {
-        let layout = built.taffy.layout(node).unwrap();
-        let row = index / self.num_cols;
-        let col = index % self.num_cols;
+        caption: Option<&String>,
+    ) -> Result<Pt, BuildError> {
+        match caption {
+            Some(caption) => Ok(builder
+                .measure_text_manuel(caption, &self.font, Some(built.width))?
+                .1),
+            None => Ok(Pt(0.0)),
+        }
+    }
 
-        // Fill rect
-        let color = if is_header {
-            printpdf::Color::Greyscale(Greyscale::new(0.9, None))
-        } else {
-            match row % 2 {
-                0 => printpdf::Color::Greyscale(Greyscale::new(0.95, None)),
-                1 => printpdf::Color::Greyscale(Greyscale::new(1.0, None)),
-                _ => unreachable!(),
-            }
+    /// Builds the caption at the cursor and returns its height
+    fn build_caption(
+        &self,
+        builder: &mut ElementBuilder,
+        built: &BuiltTable,
+        caption: Option<&String>,
+        align: TextAlign,
+        draw: bool,
+    ) -> Result<Pt, BuildError> {
+        let Some(text) = caption else {
+            return Ok(Pt(0.0));
         };
 
-        builder.fill_rect_dont_change_cursor(Pt(layout.size.width), Pt(layout.size.height), color);
-
-        if let Some(content) = built.taffy.get_node_context(node) {
-            builder.push_text_dont_change_cursor(
-                &content.content,
-                &content.font,
+        let height = self.caption_height(builder, built, caption)?;
+        if draw {
+            builder.push_text_aligned_dont_change_cursor(
+                text,
+                &self.font,
                 Point {
-                    x: Pt(layout.padding.left),
-                    y: Pt(layout.padding.top),
+                    x: Pt(0.0),
+                    y: Pt(0.0),
                 },
-                Some(Pt(layout.content_box_width() + 3.0)),
-            );
-            builder.cursor.x += Pt(layout.size.width);
+                Some(built.width),
+                align,
+            )?;
+        }
+        builder.advance_cursor(height);
+
+        Ok(height)
+    }
+
+    /// Space needed at the bottom of the page, before the table continues on the next page
+    fn page_end_height(
+        &self,
+        builder: &ElementBuilder,
+        built: &BuiltTable,
+        rows: Range<usize>,
+    ) -> Result<Pt, BuildError> {
+        Ok(self.subtotal_height(builder, built, rows)?
+            + self.caption_height(builder, built, self.caption_before_break.as_ref())?)
+    }
+
+    /// Finishes the current page with the subtotal of the rows, that started on it, and continues
+    /// below the header on the next page. `next_row` is the first row of the next page.
+    ///
+    /// Returns the height of everything added around the rows.
+    fn continue_on_next_page(
+        &self,
+        builder: &mut ElementBuilder,
+        built: &BuiltTable,
+        page: &mut TablePage,
+        next_row: usize,
+        draw: bool,
+    ) -> Result<Pt, BuildError> {
+        let mut height = self.build_subtotal(builder, built, page.first_row..next_row, draw)?;
+        if draw {
+            self.draw_outer_border(builder, built, page.top);
         }
-        if col == self.num_cols - 1 {
-            builder.reset_cursor_x();
-            if builder.advance_cursor(Pt(layout.size.height)) {
-                self.build_headers(builder, built);
+        height += self.build_caption(
+            builder,
+            built,
+            self.caption_before_break.as_ref(),
+            TextAlign::Right,
+            draw,
+        )?;
+        builder.next_page();
+
+        height += self.build_caption(
+            builder,
+            built,
+            self.caption_after_break.as_ref(),
+            TextAlign::Left,
+            draw,
+        )?;
+        *page = TablePage {
+            top: builder.cursor.y,
+            first_row: next_row,
+            continued: true,
+        };
+        self.build_headers(builder, built, draw)?;
+
+        Ok(height + built.rows_height(0..built.header_rows))
+    }
+
+    /// Builds the rows and splits them across pages, where they don't fit. Returns the height
+    /// including everything added around the rows at the page breaks.
+    fn build_split_rows(
+        &self,
+        builder: &mut ElementBuilder,
+        built: &BuiltTable,
+        rows: Range<usize>,
+        page: &mut TablePage,
+        draw: bool,
+    ) -> Result<Pt, BuildError> {
+        let rows_height = built.rows_height(rows.clone());
+        let mut start = Pt(0.0);
+        let mut height = Pt(0.0);
+        let mut new_page = false;
+
+        loop {
+            let page_end = self.page_end_height(builder, built, page.first_row..rows.end)?;
+            let available = builder.remaining_height_from_cursor() - page_end;
+            // Without any space left, the rest overflows instead of adding empty pages
+            if rows_height - start <= available || (new_page && available <= Pt(0.0)) {
+                if draw && builder.cursor.y < page.top {
+                    self.draw_row_separator(builder, built);
+                }
+                self.build_rows(builder, built, rows, start..rows_height, draw)?;
+                return Ok(height + rows_height - start);
+            }
+
+            let mut end =
+                self.split_position(builder, built, rows.clone(), start, start + available)?;
+            if end <= start && new_page {
+                // Not even a single line fits on an empty page, so it is cut in half
+                end = start + available;
+            }
+
+            if end > start {
+                if draw && builder.cursor.y < page.top {
+                    self.draw_row_separator(builder, built);
+                }
+                self.build_rows(builder, built, rows.clone(), start..end, draw)?;
+                height += end - start;
+                start = end;
             }
+
+            // The rows count for the subtotal of the page they start on
+            let next_row = if start > Pt(0.0) {
+                rows.end
+            } else {
+                rows.start
+            };
+            height += self.continue_on_next_page(builder, built, page, next_row, draw)?;
+            new_page = true;
         }
     }
 
-    fn row_height(built: &BuiltTable, nodes: &[NodeId]) -> printpdf::Pt {
-        let mut res = Pt(0.0);
-        for node in nodes.iter() {
-            let layout = built.taffy.layout(*node).unwrap();
-            res = res.max(Pt(layout.size.height))
+    /// Builds the whole table and returns its height. Only the cursor is moved, if `draw` is
+    /// false.
+    ///
+    /// Rows that are connected by cells spanning multiple rows are split together, and the header
+    /// is repeated on every new page. The footer is kept together after the last row.
+    fn build_table(
+        &self,
+        builder: &mut ElementBuilder,
+        built: &BuiltTable,
+        draw: bool,
+    ) -> Result<Pt, BuildError> {
+        let header_height = built.rows_height(0..built.header_rows);
+        let footer = built.footer_start()..built.row_heights.len();
+        let footer_height = built.rows_height(footer.clone());
+        let page_height = builder.page_style().inner_height().into_pt();
+        let groups = built.row_groups();
+        let mut height = header_height;
+
+        // The header is kept together with the first rows
+        let first_rows = groups.first().cloned().unwrap_or(footer.clone());
+        let first_height = header_height
+            + built.rows_height(first_rows.clone())
+            + self.page_end_height(builder, built, first_rows)?;
+        if first_height > builder.remaining_height_from_cursor() && first_height <= page_height {
+            builder.next_page();
+        }
+        let mut page = TablePage {
+            top: builder.cursor.y,
+            first_row: built.header_rows,
+            continued: false,
+        };
+        self.build_headers(builder, built, draw)?;
+
+        for rows in groups {
+            let rows_height = built.rows_height(rows.clone());
+            if self.is_splittable(built, rows.clone()) {
+                height += self.build_split_rows(builder, built, rows, &mut page, draw)?;
+                continue;
+            }
+
+            let page_end = self.page_end_height(builder, built, page.first_row..rows.end)?;
+            let next_page_end = self.page_end_height(builder, built, rows.clone())?;
+            if rows_height + page_end > builder.remaining_height_from_cursor()
+                && header_height + rows_height + next_page_end <= page_height
+            {
+                height +=
+                    self.continue_on_next_page(builder, built, &mut page, rows.start, draw)?;
+            }
+
+            if draw && builder.cursor.y < page.top {
+                self.draw_row_separator(builder, built);
+            }
+            self.build_rows(builder, built, rows, Pt(0.0)..rows_height, draw)?;
+            height += rows_height;
+        }
+
+        // The last page only gets a subtotal, if the table spans multiple pages
+        let last_rows = page.first_row..footer.start;
+        let subtotal_height = if page.continued {
+            self.subtotal_height(builder, built, last_rows.clone())?
+        } else {
+            Pt(0.0)
+        };
+        if footer_height > Pt(0.0)
+            && subtotal_height + footer_height > builder.remaining_height_from_cursor()
+            && header_height + footer_height <= page_height
+        {
+            height += self.continue_on_next_page(builder, built, &mut page, footer.start, draw)?;
+        } else if page.continued {
+            height += self.build_subtotal(builder, built, last_rows, draw)?;
+        }
+
+        if footer_height > Pt(0.0) {
+            if draw && builder.cursor.y < page.top {
+                self.draw_row_separator(builder, built);
+            }
+            self.build_rows(builder, built, footer, Pt(0.0)..footer_height, draw)?;
+            height += footer_height;
+        }
+
+        if draw {
+            self.draw_outer_border(builder, built, page.top);
         }
-        res
+        Ok(height)
     }
 }
 
@@ -107,96 +1429,195 @@ impl Element for Table {
         "Table"
     }
 
-    fn calculate_width<'a>(
-        &self,
-        builder: &super::element_builder::ElementBuilder<'a>,
-    ) -> printpdf::Pt {
-        let built = BuiltTable::build(self, builder);
-        let root_layout = built.taffy.layout(built.root).unwrap();
+    fn calculate_width<'a>(&self, builder: &ElementBuilder<'a>) -> Result<Pt, BuildError> {
+        let built = BuiltTable::build(self, builder)?;
+        let root_layout = built.taffy.layout(built.root).map_err(layout_error)?;
 
-        Pt(root_layout.size.width)
+        Ok(Pt(root_layout.size.width))
     }
 
-    fn calculate_height<'a>(
-        &self,
-        builder: &super::element_builder::ElementBuilder<'a>,
-    ) -> printpdf::Pt {
-        let built = BuiltTable::build(self, builder);
+    fn calculate_height<'a>(&self, builder: &ElementBuilder<'a>) -> Result<Pt, BuildError> {
+        let built = BuiltTable::build(self, builder)?;
         let mut mut_builder = builder.clone();
-        let mut height = Pt(0.0);
-        if let Some(header_cells) = built.header_cells() {
-            let header_height = Self::row_height(&built, header_cells);
-            height += header_height;
-            mut_builder.advance_cursor(header_height);
-        }
-
-        for row in 0..self.content.len() {
-            let cells = built.row_cells(row);
-            let row_height = Self::row_height(&built, cells);
-            height += row_height;
-            if mut_builder.advance_cursor(row_height) {
-                if let Some(header_cells) = built.header_cells() {
-                    let header_height = Self::row_height(&built, header_cells);
-                    height += header_height;
-                    mut_builder.advance_cursor(header_height);
-                }
-            }
+
+        self.build_table(&mut mut_builder, &built, false)
+    }
+
+    fn build<'a>(&self, builder: &mut ElementBuilder<'a>) -> Result<(), BuildError> {
+        let built = BuiltTable::build(self, builder)?;
+        for diagnostic in &built.diagnostics {
+            builder.push_diagnostic(diagnostic.clone());
         }
 
-        height
+        self.build_table(builder, &built, true)?;
+        Ok(())
+    }
+}
+
+/// The content of a cell after formatting
+enum CellValue<'t> {
+    Text(Cow<'t, str>),
+    Element(&'t dyn Element),
+}
+
+struct CellContent<'t> {
+    value: CellValue<'t>,
+    /// Only used for text
+    font: Font,
+    align: TextAlign,
+    /// Space kept free right of the text, so the decimal separators of a column line up
+    inset: Pt,
+    /// Overrides the fill of the row
+    background: Option<Color>,
+}
+
+impl CellContent<'_> {
+    /// Width available for the text of the cell
+    fn text_width(&self, layout: &Layout) -> Pt {
+        Pt(layout.content_box_width()) + TEXT_SLACK - self.inset
     }
 
-    fn build<'a>(&self, builder: &mut super::element_builder::ElementBuilder<'a>) {
-        let built = BuiltTable::build(self, builder);
+    /// Measures the content for the available width, without the padding of the cell
+    fn measure(
+        &self,
+        builder: &ElementBuilder,
+        available_width: AvailableSpace,
+    ) -> Result<Size<f32>, BuildError> {
+        match &self.value {
+            CellValue::Text(text) => {
+                let width = match available_width {
+                    AvailableSpace::Definite(width) => Pt(width),
+                    AvailableSpace::MinContent => {
+                        builder.measure_text_min_content(text, &self.font)? + self.inset
+                    }
+                    AvailableSpace::MaxContent => {
+                        builder.measure_text_manuel(text, &self.font, None)?.0 + self.inset
+                    }
+                };
+                let height = builder
+                    .measure_text_manuel(text, &self.font, Some(width - self.inset))?
+                    .1;
+
+                Ok(Size {
+                    width: width.0,
+                    height: height.0,
+                })
+            }
+            CellValue::Element(element) => {
+                let width = match available_width {
+                    AvailableSpace::Definite(width) => Pt(width),
+                    AvailableSpace::MinContent | AvailableSpace::MaxContent => builder
+                        .measure_child_width(*element)?
+                        .min(builder.remaining_width_from_cursor()),
+                };
+                let cell_builder = builder.generate_cell_builder(
+                    Point {
+                        x: Pt(0.0),
+                        y: Pt(0.0),
+                    },
+                    width,
+                );
+                let height = cell_builder.measure_child_height(*element)?;
 
-        self.build_headers(builder, &built);
-        for (i, node) in built.content_cells().iter().enumerate() {
-            self.build_cell(builder, &built, i, *node, false);
+                Ok(Size {
+                    width: width.0,
+                    height: height.0,
+                })
+            }
         }
     }
 }
 
-struct CellContent {
-    content: String,
-    font: Font,
+/// A cell placed on the grid. Rows are counted from the first header row.
+struct BuiltCell {
+    node: NodeId,
+    row: usize,
+    col: usize,
+    rowspan: usize,
 }
 
-struct BuiltTable {
-    taffy: TaffyTree<CellContent>,
+struct BuiltTable<'t> {
+    taffy: TaffyTree<CellContent<'t>>,
     root: NodeId,
-    has_header: bool,
-    cells: Vec<NodeId>,
-    num_cols: usize,
+    header_rows: usize,
+    cells: Vec<BuiltCell>,
+    row_heights: Vec<Pt>,
+    footer_rows: usize,
+    column_widths: Vec<Pt>,
+    /// Sum of the column widths
+    width: Pt,
+    /// Problems with the rows, that are reported when the table is built
+    diagnostics: Vec<Diagnostic>,
 }
 
-impl BuiltTable {
-    fn header_cells(&self) -> Option<&[NodeId]> {
-        if self.has_header {
-            Some(&self.cells[0..self.num_cols])
-        } else {
-            None
-        }
+impl<'t> BuiltTable<'t> {
+    /// Index of the first footer row
+    fn footer_start(&self) -> usize {
+        self.row_heights.len() - self.footer_rows
+    }
+
+    fn row_top(&self, row: usize) -> Pt {
+        self.rows_height(0..row)
+    }
+
+    fn rows_height(&self, rows: Range<usize>) -> Pt {
+        self.row_heights[rows]
+            .iter()
+            .fold(Pt(0.0), |total, height| total + *height)
     }
 
-    fn content_cells(&self) -> &[NodeId] {
-        if self.has_header {
-            &self.cells[self.num_cols..]
+    /// Describes the position of the cell for diagnostics, counting from 1
+    fn position(&self, cell: &BuiltCell) -> String {
+        if cell.row < self.header_rows {
+            format!("header row {}, column {}", cell.row + 1, cell.col + 1)
+        } else if cell.row >= self.footer_start() {
+            format!(
+                "footer row {}, column {}",
+                cell.row - self.footer_start() + 1,
+                cell.col + 1
+            )
         } else {
-            &self.cells
+            format!(
+                "row {}, column {}",
+                cell.row - self.header_rows + 1,
+                cell.col + 1
+            )
         }
     }
 
-    fn row_cells(&self, row: usize) -> &[NodeId] {
-        let start = row * self.num_cols;
-        let end = start + self.num_cols;
-        if start >= self.cells.len() {
-            return &[];
+    /// Splits the body rows into groups, that are connected by cells spanning multiple rows
+    fn row_groups(&self) -> Vec<Range<usize>> {
+        let row_count = self.footer_start();
+        let mut span_end = (0..row_count).map(|row| row + 1).collect::<Vec<_>>();
+        for cell in self.cells.iter() {
+            if cell.row < row_count {
+                span_end[cell.row] = span_end[cell.row].max(cell.row + cell.rowspan);
+            }
+        }
+
+        let mut groups = Vec::new();
+        let mut start = self.header_rows;
+        while start < row_count {
+            let mut end = span_end[start];
+            let mut row = start + 1;
+            while row < end {
+                end = end.max(span_end[row]);
+                row += 1;
+            }
+
+            groups.push(start..end);
+            start = end;
         }
 
-        &self.cells[start..end]
+        groups
     }
 
-    fn content_to_cell(taffy: &mut TaffyTree<CellContent>, cell: CellContent) -> taffy::NodeId {
+    fn content_to_cell(
+        taffy: &mut TaffyTree<CellContent<'t>>,
+        cell: CellContent<'t>,
+        placement: &Placement,
+        padding: Rect<LengthPercentage>,
+    ) -> Result<NodeId, BuildError> {
         taffy
             .new_leaf_with_context(
                 Style {
@@ -204,19 +1625,27 @@ impl BuiltTable {
                         width: auto(),
                         height: auto(),
                     },
-                    padding: Rect::length(5.0),
+                    padding,
                     overflow: taffy::Point {
                         x: Overflow::Clip,
                         y: Overflow::Visible,
                     },
+                    grid_row: Line {
+                        start: line(placement.row as i16 + 1),
+                        end: span(placement.rowspan as u16),
+                    },
+                    grid_column: Line {
+                        start: line(placement.col as i16 + 1),
+                        end: span(placement.colspan as u16),
+                    },
                     ..Default::default()
                 },
                 cell,
             )
-            .unwrap()
+            .map_err(layout_error)
     }
 
-    fn build(value: &Table, builder: &super::element_builder::ElementBuilder) -> Self {
+    fn build(value: &'t Table, builder: &ElementBuilder) -> Result<Self, BuildError> {
         let mut taffy = TaffyTree::<CellContent>::new();
         taffy.disable_rounding();
 
@@ -234,44 +1663,83 @@ impl BuiltTable {
                 width: length(builder.remaining_width_from_cursor().0),
                 height: auto(),
             },
-            grid_template_columns: vec![
-                // minmax(
-                //     MinTrackSizingFunction::min_content(),
-                //     MaxTrackSizingFunction::fr(1.0)
-                // );
-                auto();
-                value.num_cols
-            ],
+            grid_template_columns: value
+                .column_widths
+                .iter()
+                .map(|width| width.track_sizing().into())
+                .collect(),
             ..Default::default()
         };
 
-        let cells = value
-            .header
-            .clone()
-            .map(|header| {
-                header
-                    .0
-                    .iter()
-                    .map(|cell| CellContent {
-                        content: cell.clone(),
-                        font: header.1.clone(),
-                    })
-                    .collect()
+        let header_rows = value.header.len();
+        let (header_cells, header_diagnostics) =
+            place_cells(&value.header, value.num_cols, "header row");
+        let (content_cells, content_diagnostics) =
+            place_cells(&value.content, value.num_cols, "row");
+        let (footer_cells, footer_diagnostics) =
+            place_cells(&value.footer, value.num_cols, "footer row");
+        let diagnostics = header_diagnostics
+            .into_iter()
+            .chain(content_diagnostics)
+            .chain(footer_diagnostics)
+            .collect();
+
+        let header_cells = header_cells
+            .into_iter()
+            .map(|(cell, placement)| (cell, value.header_font(), placement));
+        let content_cells = content_cells.into_iter().map(|(cell, placement)| {
+            let placement = Placement {
+                row: placement.row + header_rows,
+                ..placement
+            };
+            (cell, &value.font, placement)
+        });
+        let footer_start = header_rows + value.content.len();
+        let footer_cells = footer_cells.into_iter().map(|(cell, placement)| {
+            let placement = Placement {
+                row: placement.row + footer_start,
+                ..placement
+            };
+            (cell, &value.font, placement)
+        });
+
+        let cell_padding = &value.style.cell_padding;
+        let padding = Rect {
+            left: length(cell_padding.left.into_pt().0),
+            right: length(cell_padding.right.into_pt().0),
+            top: length(cell_padding.top.into_pt().0),
+            bottom: length(cell_padding.bottom.into_pt().0),
+        };
+
+        let mut contents = header_cells
+            .chain(content_cells)
+            .chain(footer_cells)
+            .map(|(cell, font, placement)| {
+                let content = value.cell_content(cell, font, &placement, header_rows);
+                (cell, content, placement)
             })
-            .unwrap_or(Vec::new())
+            .collect::<Vec<_>>();
+        value.align_decimals(builder, &mut contents)?;
+
+        let cells = contents
             .into_iter()
-            .chain(value.content.iter().flat_map(|row| {
-                row.iter().map(|cell| CellContent {
-                    content: cell.clone(),
-                    font: value.font.clone(),
+            .map(|(_, content, placement)| {
+                Ok(BuiltCell {
+                    node: Self::content_to_cell(&mut taffy, content, &placement, padding)?,
+                    row: placement.row,
+                    col: placement.col,
+                    rowspan: placement.rowspan,
                 })
-            }))
-            .map(|cell| Self::content_to_cell(&mut taffy, cell))
-            .collect::<Vec<_>>();
+            })
+            .collect::<Result<Vec<_>, BuildError>>()?;
 
+        let nodes = cells.iter().map(|cell| cell.node).collect::<Vec<_>>();
         let root = taffy
-            .new_with_children(grid_style, cells.as_slice())
-            .unwrap();
+            .new_with_children(grid_style, nodes.as_slice())
+            .map_err(layout_error)?;
+
+        // The measure function can't fail, so the first error is kept and returned afterwards
+        let mut measure_error = None;
 
         taffy
             .compute_layout_with_measure(
@@ -286,48 +1754,548 @@ impl BuiltTable {
                         return Size { width, height };
                     }
 
-                    match node_context {
-                        None => Size::ZERO,
-                        Some(content) => {
-                            let min_text_width = builder
-                                .measure_text_min_content(&content.content, &content.font)
-                                .0;
-                            let max_text_width = builder
-                                .measure_text_manuel(&content.content, &content.font, None)
-                                .0
-                                .0;
-
-                            let width = match available_space.width {
-                                AvailableSpace::Definite(w) => {
-                                    // w.min(max_text_width).max(min_text_width)
-                                    w
-                                }
-                                AvailableSpace::MinContent => min_text_width,
-                                AvailableSpace::MaxContent => max_text_width,
-                            };
-
-                            let measured = builder.measure_text_manuel(
-                                &content.content,
-                                &content.font,
-                                Some(Pt(width)),
-                            );
-
-                            Size {
-                                width,
-                                height: measured.1.0,
-                            }
-                        }
-                    }
+                    let Some(content) = node_context else {
+                        return Size::ZERO;
+                    };
+
+                    let available_width = match known_dimensions.width {
+                        Some(width) => AvailableSpace::Definite(width),
+                        None => available_space.width,
+                    };
+
+                    content
+                        .measure(builder, available_width)
+                        .unwrap_or_else(|e| {
+                            measure_error.get_or_insert(e);
+                            Size::ZERO
+                        })
                 },
             )
-            .unwrap();
+            .map_err(layout_error)?;
 
-        Self {
+        if let Some(error) = measure_error {
+            return Err(error);
+        }
+
+        // Rows spanned by cells get their height from the grid, not from a single cell
+        let DetailedLayoutInfo::Grid(grid) = taffy.detailed_layout_info(root) else {
+            return Err(
+                BuildErrorKind::Layout("the table was not laid out as a grid".into()).into(),
+            );
+        };
+        let mut row_heights = grid
+            .rows
+            .sizes
+            .iter()
+            .skip(grid.rows.negative_implicit_tracks as usize)
+            .map(|height| Pt(*height))
+            .collect::<Vec<_>>();
+        row_heights.resize(footer_start + value.footer.len(), Pt(0.0));
+        let column_widths = grid
+            .columns
+            .sizes
+            .iter()
+            .skip(grid.columns.negative_implicit_tracks as usize)
+            .map(|width| Pt(*width))
+            .collect::<Vec<_>>();
+        let width = column_widths
+            .iter()
+            .fold(Pt(0.0), |total, width| total + *width);
+
+        Ok(Self {
             taffy,
             root,
-            has_header: value.header.is_some(),
+            header_rows,
             cells,
-            num_cols: value.num_cols,
+            row_heights,
+            footer_rows: value.footer.len(),
+            column_widths,
+            width,
+            diagnostics,
+        })
+    }
+}
+
+/// Position of a cell on the grid
+struct Placement {
+    row: usize,
+    col: usize,
+    rowspan: usize,
+    colspan: usize,
+}
+
+/// Places the cells of the rows on the grid, skipping the columns covered by cells of the rows
+/// above.
+///
+/// Rows with too many cells are cut off at the last column and rows with too few cells stay empty
+/// at the end. Cells don't span past the last row. All of these are reported with `row_name`.
+fn place_cells<'t>(
+    rows: &'t [Vec<TableCell>],
+    num_cols: usize,
+    row_name: &str,
+) -> (Vec<(&'t TableCell, Placement)>, Vec<Diagnostic>) {
+    let mut covered = vec![vec![false; num_cols]; rows.len()];
+    let mut placements = Vec::new();
+    let mut diagnostics = Vec::new();
+    let mut report = |row: usize, problem: String| {
+        diagnostics.push(Diagnostic::new(
+            DiagnosticKind::TableGrid,
+            format!("{row_name} {} {problem}", row + 1),
+        ));
+    };
+
+    for (row, cells) in rows.iter().enumerate() {
+        let mut col = 0;
+        let mut cut_off = false;
+        for cell in cells {
+            while col < num_cols && covered[row][col] {
+                col += 1;
+            }
+            if col + cell.colspan > num_cols {
+                cut_off = true;
+            }
+            if col >= num_cols {
+                break;
+            }
+
+            let colspan = cell.colspan.min(num_cols - col);
+            let rowspan = cell.rowspan.min(rows.len() - row);
+            if rowspan < cell.rowspan {
+                report(
+                    row,
+                    format!(
+                        "has a cell spanning {} rows, but only {rowspan} are left",
+                        cell.rowspan
+                    ),
+                );
+            }
+            for covered_row in covered[row..row + rowspan].iter_mut() {
+                covered_row[col..col + colspan].fill(true);
+            }
+
+            placements.push((
+                cell,
+                Placement {
+                    row,
+                    col,
+                    rowspan,
+                    colspan,
+                },
+            ));
+            col += colspan;
+        }
+
+        if cut_off {
+            report(
+                row,
+                format!("has more cells than the table has columns ({num_cols}), they are cut off"),
+            );
+        } else if covered[row].contains(&false) {
+            report(
+                row,
+                format!("has fewer cells than the table has columns ({num_cols})"),
+            );
+        }
+    }
+
+    (placements, diagnostics)
+}
+
+fn layout_error(error: taffy::TaffyError) -> BuildError {
+    BuildErrorKind::Layout(error.to_string()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use printpdf::{Op, TextItem};
+
+    use super::*;
+    use crate::generate::{
+        document::Document,
+        document_builder::{DocumentBuilder, DocumentFormat},
+    };
+
+    const ROBOTO: &[u8] = include_bytes!("../../../Roboto/static/Roboto-Regular.ttf");
+
+    fn a6_document() -> (Document, Font) {
+        let mut document = DocumentBuilder::new("test")
+            .format(DocumentFormat::A6)
+            .build();
+        let font = document.add_font(ROBOTO).unwrap();
+        (document, font)
+    }
+
+    /// The lines of text on every page, the document may only have font diagnostics
+    fn page_texts(document: Document) -> Vec<Vec<String>> {
+        let (pdf_document, diagnostics) = document.generate_document().unwrap();
+        assert!(
+            diagnostics
+                .iter()
+                .all(|diagnostic| diagnostic.kind == DiagnosticKind::Font),
+            "{diagnostics:?}"
+        );
+
+        pdf_document
+            .pages
+            .iter()
+            .map(|page| {
+                page.ops
+                    .iter()
+                    .filter_map(|op| match op {
+                        Op::WriteText { items, .. } => Some(
+                            items
+                                .iter()
+                                .map(|item| match item {
+                                    TextItem::Text(text) => text.as_str(),
+                                    // Spaces are written as offsets
+                                    TextItem::Offset(_) => " ",
+                                })
+                                .collect::<String>()
+                                .trim()
+                                .to_string(),
+                        ),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn keeps_the_decimals_of_the_cell() {
+        let plain = NumericFormat::new();
+        assert_eq!(plain.format("12.50").as_deref(), Some("12.50"));
+        assert_eq!(plain.format(" 7 ").as_deref(), Some("7"));
+        assert_eq!(plain.format("+.5").as_deref(), Some("0.5"));
+        assert_eq!(plain.format("-.5").as_deref(), Some("-0.5"));
+        assert_eq!(plain.format("3.").as_deref(), Some("3"));
+    }
+
+    #[test]
+    fn rounds_to_fixed_decimals() {
+        let two = NumericFormat::new().with_decimals(2);
+        assert_eq!(two.format("1.005e1").as_deref(), Some("10.05"));
+        assert_eq!(two.format("2").as_deref(), Some("2.00"));
+        assert_eq!(
+            NumericFormat::new()
+                .with_decimals(0)
+                .format("2.5")
+                .as_deref(),
+            Some("3")
+        );
+        assert_eq!(two.format("1.005").as_deref(), Some("1.01"));
+        assert_eq!(two.format("-1.005").as_deref(), Some("-1.01"));
+        assert_eq!(two.format("9.995").as_deref(), Some("10.00"));
+        assert_eq!(two.format("0.5e-2").as_deref(), Some("0.01"));
+    }
+
+    #[test]
+    fn drops_the_sign_of_values_rounded_to_zero() {
+        let two = NumericFormat::new().with_decimals(2);
+        assert_eq!(two.format("-0.001").as_deref(), Some("0.00"));
+        assert_eq!(two.format("-0.005001").as_deref(), Some("-0.01"));
+        assert_eq!(NumericFormat::new().format("-0").as_deref(), Some("0"));
+        assert_eq!(NumericFormat::new().format("-0.0").as_deref(), Some("0.0"));
+    }
+
+    #[test]
+    fn writes_exponent_notation_out() {
+        let plain = NumericFormat::new();
+        assert_eq!(plain.format("1e3").as_deref(), Some("1000"));
+        assert_eq!(plain.format("1.5E-3").as_deref(), Some("0.0015"));
+        assert_eq!(plain.format("-2.5e2").as_deref(), Some("-250"));
+    }
+
+    #[test]
+    fn groups_thousands() {
+        let grouped = NumericFormat::new().with_thousands_separator(',');
+        assert_eq!(grouped.format("123").as_deref(), Some("123"));
+        assert_eq!(grouped.format("1234").as_deref(), Some("1,234"));
+        assert_eq!(grouped.format("-123456").as_deref(), Some("-123,456"));
+        assert_eq!(
+            grouped.format("1234567.8912").as_deref(),
+            Some("1,234,567.8912")
+        );
+
+        let german = NumericFormat::new()
+            .with_thousands_separator('.')
+            .with_decimal_separator(',')
+            .with_decimals(2);
+        assert_eq!(german.format("1234.5").as_deref(), Some("1.234,50"));
+    }
+
+    #[test]
+    fn puts_the_sign_before_the_prefix() {
+        let dollars = NumericFormat::new()
+            .with_prefix("$")
+            .with_thousands_separator(',')
+            .with_decimals(2);
+        assert_eq!(dollars.format("1234").as_deref(), Some("$1,234.00"));
+        assert_eq!(dollars.format("-1234").as_deref(), Some("-$1,234.00"));
+
+        let euros = NumericFormat::new().with_suffix(" EUR");
+        assert_eq!(euros.format("-3.5").as_deref(), Some("-3.5 EUR"));
+    }
+
+    #[test]
+    fn keeps_text_that_is_not_a_number() {
+        let plain = NumericFormat::new();
+        assert_eq!(plain.format("n/a"), None);
+        assert_eq!(plain.format(""), None);
+        assert_eq!(plain.format("inf"), None);
+        assert_eq!(plain.format("NaN"), None);
+        assert_eq!(plain.format("1,234"), None);
+    }
+
+    #[test]
+    fn finds_the_decimal_part() {
+        assert_eq!(decimal_part("1,234.56", '.'), ".56");
+        assert_eq!(decimal_part("1.234,5", ','), ",5");
+        assert_eq!(decimal_part("12", '.'), "");
+        assert_eq!(decimal_part("12 EUR", '.'), " EUR");
+        assert_eq!(decimal_part("-$3.50 USD", '.'), ".50 USD");
+        assert_eq!(decimal_part("n/a", '.'), "n/a");
+    }
+
+    /// Row, column, rowspan and colspan of a placed cell
+    type Position = (usize, usize, usize, usize);
+
+    fn grid(rows: &[Vec<TableCell>], num_cols: usize) -> (Vec<Position>, Vec<String>) {
+        let (placements, diagnostics) = place_cells(rows, num_cols, "row");
+        let placements = placements
+            .into_iter()
+            .map(|(_, placement)| {
+                (
+                    placement.row,
+                    placement.col,
+                    placement.rowspan,
+                    placement.colspan,
+                )
+            })
+            .collect();
+        let diagnostics = diagnostics
+            .into_iter()
+            .map(|diagnostic| {
+                assert_eq!(diagnostic.kind, DiagnosticKind::TableGrid);
+                diagnostic.message
+            })
+            .collect();
+
+        (placements, diagnostics)
+    }
+
+    #[test]
+    fn places_spanning_cells() {
+        let rows = vec![
+            vec![TableCell::new("a").with_colspan(2), TableCell::new("b")],
+            vec![
+                TableCell::new("c"),
+                TableCell::new("d"),
+                TableCell::new("e"),
+            ],
+        ];
+
+        let (placements, diagnostics) = grid(&rows, 3);
+        assert_eq!(
+            placements,
+            vec![
+                (0, 0, 1, 2),
+                (0, 2, 1, 1),
+                (1, 0, 1, 1),
+                (1, 1, 1, 1),
+                (1, 2, 1, 1)
+            ]
+        );
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn skips_columns_covered_by_rowspans() {
+        let rows = vec![
+            vec![
+                TableCell::new("a").with_rowspan(2),
+                TableCell::new("b"),
+                TableCell::new("c").with_rowspan(3),
+            ],
+            vec![TableCell::new("d")],
+            vec![TableCell::new("e"), TableCell::new("f")],
+        ];
+
+        let (placements, diagnostics) = grid(&rows, 3);
+        assert_eq!(
+            placements,
+            vec![
+                (0, 0, 2, 1),
+                (0, 1, 1, 1),
+                (0, 2, 3, 1),
+                (1, 1, 1, 1),
+                (2, 0, 1, 1),
+                (2, 1, 1, 1),
+            ]
+        );
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn cuts_rowspans_at_the_last_row() {
+        let rows = vec![
+            vec![TableCell::new("a").with_rowspan(3), TableCell::new("b")],
+            vec![TableCell::new("c")],
+        ];
+
+        let (placements, diagnostics) = grid(&rows, 2);
+        assert_eq!(placements, vec![(0, 0, 2, 1), (0, 1, 1, 1), (1, 1, 1, 1)]);
+        assert_eq!(
+            diagnostics,
+            vec!["row 1 has a cell spanning 3 rows, but only 2 are left"]
+        );
+    }
+
+    #[test]
+    fn cuts_off_rows_with_too_many_cells() {
+        let rows = vec![
+            vec![
+                TableCell::new("a"),
+                TableCell::new("b"),
+                TableCell::new("c"),
+            ],
+            vec![TableCell::new("d"), TableCell::new("e").with_colspan(2)],
+        ];
+
+        let (placements, diagnostics) = grid(&rows, 2);
+        assert_eq!(
+            placements,
+            vec![(0, 0, 1, 1), (0, 1, 1, 1), (1, 0, 1, 1), (1, 1, 1, 1)]
+        );
+        assert_eq!(
+            diagnostics,
+            vec![
+                "row 1 has more cells than the table has columns (2), they are cut off",
+                "row 2 has more cells than the table has columns (2), they are cut off",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_rows_with_too_few_cells() {
+        let rows = vec![
+            vec![TableCell::new("a")],
+            vec![TableCell::new("b"), TableCell::new("c")],
+        ];
+
+        let (placements, diagnostics) = grid(&rows, 2);
+        assert_eq!(placements, vec![(0, 0, 1, 1), (1, 0, 1, 1), (1, 1, 1, 1)]);
+        assert_eq!(
+            diagnostics,
+            vec!["row 1 has fewer cells than the table has columns (2)"]
+        );
+    }
+
+    #[test]
+    fn repeats_the_header_subtotal_and_captions_on_every_page() {
+        let (mut document, font) = a6_document();
+        let mut table = Table::new(2, font)
+            .with_page_subtotal(|rows| {
+                vec![
+                    TableCell::new(format!("Subtotal of {} rows", rows.len())),
+                    TableCell::new(""),
+                ]
+            })
+            .with_caption_before_break("continued on next page")
+            .with_caption_after_break("continued");
+        table.add_header_row(["Name", "Amount"]);
+        for row in 0..40 {
+            table.add_row([format!("Item {row}"), "1".to_string()]);
+        }
+        table.add_footer_row(["Total", "40"]);
+        document.push(table);
+
+        let pages = page_texts(document);
+        let last = pages.len() - 1;
+        assert!(last > 0);
+
+        let mut subtotal_rows = 0;
+        for (index, lines) in pages.iter().enumerate() {
+            let has = |text: &str| lines.iter().any(|line| line == text);
+            assert!(has("Name"), "no header on page {}", index + 1);
+            assert_eq!(has("continued on next page"), index < last);
+            assert_eq!(has("continued"), index > 0);
+            assert_eq!(has("Total"), index == last);
+
+            let subtotals = lines
+                .iter()
+                .filter_map(|line| line.strip_prefix("Subtotal of "))
+                .collect::<Vec<_>>();
+            assert_eq!(subtotals.len(), 1, "page {}", index + 1);
+            subtotal_rows += subtotals[0]
+                .trim_end_matches(" rows")
+                .parse::<usize>()
+                .unwrap();
+        }
+        assert_eq!(subtotal_rows, 40);
+
+        for row in 0..40 {
+            let item = format!("Item {row}");
+            let count = pages.iter().flatten().filter(|line| **line == item).count();
+            assert_eq!(count, 1, "{item}");
+        }
+    }
+
+    #[test]
+    fn splits_a_tall_row_across_pages() {
+        let (mut document, font) = a6_document();
+        let mut table = Table::new(1, font);
+        table.add_header_row(["Notes"]);
+        table.add_row(["word ".repeat(500)]);
+        table.add_footer_row(["End"]);
+        document.push(table);
+
+        let pages = page_texts(document);
+        assert!(pages.len() > 1);
+        for (index, lines) in pages.iter().enumerate() {
+            assert!(lines.iter().any(|line| line == "Notes"));
+            assert!(lines.iter().any(|line| line.starts_with("word")));
+            assert_eq!(
+                lines.iter().any(|line| line == "End"),
+                index == pages.len() - 1
+            );
         }
     }
+
+    #[test]
+    fn splits_rows_between_lines() {
+        let (document, font) = a6_document();
+        let text = "word ".repeat(100);
+        let mut table = Table::new(1, font.clone()).with_style(TableStyle {
+            cell_padding: Padding::none(),
+            ..Default::default()
+        });
+        table.add_row([text.as_str()]);
+
+        let builder = ElementBuilder::new(&document);
+        let built = BuiltTable::build(&table, &builder).unwrap();
+        let node = built.cells[0].node;
+        let width = built
+            .taffy
+            .get_node_context(node)
+            .unwrap()
+            .text_width(built.taffy.layout(node).unwrap());
+        let rows_height = built.rows_height(0..1);
+        let max = rows_height / 2.0 + Pt(0.3);
+
+        let end = table
+            .split_position(&builder, &built, 0..1, Pt(0.0), max)
+            .unwrap();
+        let fitting = builder
+            .fitting_text_height(&text, &font, width, max)
+            .unwrap();
+        assert!(end > Pt(0.0) && end < max);
+        assert_eq!(end, fitting);
+
+        // The rest continues from the split
+        let rest = table
+            .split_position(&builder, &built, 0..1, end, end + max)
+            .unwrap();
+        assert!(rest > end && rest <= end + max);
+    }
 }
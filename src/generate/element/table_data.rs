@@ -0,0 +1,523 @@
+//! Builds a [`Table`] from CSV data with the `csv` feature, or from records implementing
+//! `serde::Serialize` with the `serde` feature. Both are read with the `csv` crate.
+
+use std::fmt;
+#[cfg(feature = "csv")]
+use std::io::Read;
+
+use crate::generate::{element::table::Table, font::Font};
+
+/// The header, if there is one, and the other rows
+type Rows = (Option<Vec<String>>, Vec<Vec<String>>);
+
+#[derive(Debug)]
+pub enum TableDataError {
+    Io(std::io::Error),
+    /// The CSV data is malformed. Lines start at 1, 0 if the line is not known.
+    Csv {
+        line: usize,
+        message: String,
+    },
+    /// A record could not be turned into a row
+    Record(String),
+    /// There are no rows to build the table from
+    Empty,
+}
+
+impl fmt::Display for TableDataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TableDataError::Io(error) => write!(f, "failed to read table data: {error}"),
+            TableDataError::Csv { line, message } => {
+                write!(f, "invalid CSV in line {line}: {message}")
+            }
+            TableDataError::Record(message) => write!(f, "invalid record: {message}"),
+            TableDataError::Empty => write!(f, "no rows to build the table from"),
+        }
+    }
+}
+
+impl std::error::Error for TableDataError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TableDataError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for TableDataError {
+    fn from(error: std::io::Error) -> Self {
+        TableDataError::Io(error)
+    }
+}
+
+impl From<csv::Error> for TableDataError {
+    fn from(error: csv::Error) -> Self {
+        let line = error
+            .position()
+            .map_or(0, |position| position.line() as usize);
+        match error.into_kind() {
+            csv::ErrorKind::Io(error) => TableDataError::Io(error),
+            csv::ErrorKind::Utf8 { err, .. } => TableDataError::Csv {
+                line,
+                message: format!("field {} is not valid UTF-8", err.field() + 1),
+            },
+            csv::ErrorKind::Serialize(message) => TableDataError::Record(message),
+            csv::ErrorKind::UnequalLengths {
+                expected_len, len, ..
+            } => TableDataError::Csv {
+                line,
+                message: format!("the record has {len} fields instead of {expected_len}"),
+            },
+            csv::ErrorKind::Deserialize { err, .. } => TableDataError::Record(err.to_string()),
+            csv::ErrorKind::Seek => TableDataError::Csv {
+                line,
+                message: "the header can't be read after seeking".to_string(),
+            },
+            _ => TableDataError::Csv {
+                line,
+                message: "the data can't be read".to_string(),
+            },
+        }
+    }
+}
+
+/// Whether the first row of the CSV data is the header of the table
+#[cfg(feature = "csv")]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum CsvHeader {
+    /// The first row is the header, if it contains no numbers, while a column below it only
+    /// contains numbers
+    #[default]
+    Detect,
+    Present,
+    Absent,
+}
+
+#[cfg(feature = "csv")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct CsvOptions {
+    delimiter: u8,
+    header: CsvHeader,
+}
+
+#[cfg(feature = "csv")]
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            header: CsvHeader::Detect,
+        }
+    }
+}
+
+#[cfg(feature = "csv")]
+impl CsvOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Separates the fields of a row, e.g. b';' or b'\t'. Defaults to b','.
+    pub fn with_delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    pub fn with_header(mut self, header: CsvHeader) -> Self {
+        self.header = header;
+        self
+    }
+}
+
+impl Table {
+    /// Reads CSV data with quoted fields as described in RFC 4180. Rows can have different
+    /// lengths, empty lines and a leading UTF-8 byte order mark are skipped. A quote, that is
+    /// not closed, extends its field to the end of the data.
+    #[cfg(feature = "csv")]
+    pub fn from_csv(
+        reader: impl Read,
+        font: Font,
+        options: CsvOptions,
+    ) -> Result<Table, TableDataError> {
+        let (header, rows) = csv_rows(reader, &options)?;
+        table_from_rows(header, rows, font)
+    }
+
+    /// Creates a row for every record, written with `csv::Writer::serialize`. Structs create a
+    /// header from the field names of the first record, tuples and sequences create a table
+    /// without a header.
+    ///
+    /// Fields are matched to the columns by their position. Field values have to be numbers,
+    /// strings, booleans, unit enum variants or options of them. Maps are not supported.
+    #[cfg(feature = "serde")]
+    pub fn from_records<T>(
+        records: impl IntoIterator<Item = T>,
+        font: Font,
+    ) -> Result<Table, TableDataError>
+    where
+        T: serde::Serialize,
+    {
+        let (header, rows) = record_rows(records)?;
+        table_from_rows(header, rows, font)
+    }
+}
+
+#[cfg(feature = "csv")]
+fn csv_rows(mut reader: impl Read, options: &CsvOptions) -> Result<Rows, TableDataError> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    // Spreadsheet programs often start their exports with a byte order mark
+    let data = data.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(&data);
+
+    let mut rows = read_rows(data, options.delimiter)?;
+    let has_header = match options.header {
+        CsvHeader::Detect => detect_header(&rows),
+        CsvHeader::Present => true,
+        CsvHeader::Absent => false,
+    };
+    let header = (has_header && !rows.is_empty()).then(|| rows.remove(0));
+
+    Ok((header, rows))
+}
+
+/// The header is only written for structs
+#[cfg(feature = "serde")]
+fn record_rows<T>(records: impl IntoIterator<Item = T>) -> Result<Rows, TableDataError>
+where
+    T: serde::Serialize,
+{
+    let mut writer = csv::WriterBuilder::new()
+        .flexible(true)
+        .from_writer(Vec::new());
+    let mut count = 0;
+    for record in records {
+        writer.serialize(record)?;
+        count += 1;
+    }
+    let data = writer
+        .into_inner()
+        .map_err(|error| TableDataError::Io(error.into_error()))?;
+
+    let mut rows = read_rows(&data, b',')?;
+    // The writer only adds a header row for structs
+    let header = (rows.len() > count).then(|| rows.remove(0));
+
+    Ok((header, rows))
+}
+
+fn read_rows(data: &[u8], delimiter: u8) -> Result<Vec<Vec<String>>, TableDataError> {
+    csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(data)
+        .into_records()
+        .map(|record| Ok(record?.iter().map(str::to_string).collect()))
+        .collect()
+}
+
+#[cfg(feature = "csv")]
+fn is_number(field: &str) -> bool {
+    field.trim().parse::<f64>().is_ok()
+}
+
+#[cfg(feature = "csv")]
+fn detect_header(rows: &[Vec<String>]) -> bool {
+    let Some((first, rest)) = rows.split_first() else {
+        return false;
+    };
+    if rest.is_empty()
+        || first
+            .iter()
+            .any(|field| field.is_empty() || is_number(field))
+    {
+        return false;
+    }
+
+    (0..first.len()).any(|col| {
+        rest.iter()
+            .all(|row| row.get(col).is_some_and(|field| is_number(field)))
+    })
+}
+
+/// Creates a table with a column for every field of the longest row. Shorter rows are filled
+/// with empty cells.
+fn table_from_rows(
+    header: Option<Vec<String>>,
+    rows: Vec<Vec<String>>,
+    font: Font,
+) -> Result<Table, TableDataError> {
+    let num_cols = header
+        .iter()
+        .chain(rows.iter())
+        .map(Vec::len)
+        .max()
+        .filter(|num_cols| *num_cols > 0)
+        .ok_or(TableDataError::Empty)?;
+    let fill = |mut row: Vec<String>| {
+        row.resize(num_cols, String::new());
+        row
+    };
+
+    let mut table = Table::new(num_cols, font);
+    if let Some(header) = header {
+        table.set_header(fill(header), None);
+    }
+    for row in rows {
+        table.add_row(fill(row));
+    }
+
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|row| row.iter().map(|field| field.to_string()).collect())
+            .collect()
+    }
+
+    #[cfg(feature = "csv")]
+    fn parse(data: &str, options: CsvOptions) -> Result<Rows, TableDataError> {
+        csv_rows(data.as_bytes(), &options)
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn reads_quoted_fields() {
+        let data = "name,quote\r\n\"Doe, Jane\",\"She said \"\"hi\"\"\"\n\"multi\nline\",\"\"\n";
+        let (header, body) =
+            parse(data, CsvOptions::new().with_header(CsvHeader::Present)).expect("valid CSV");
+
+        assert_eq!(header, Some(vec!["name".to_string(), "quote".to_string()]));
+        assert_eq!(
+            body,
+            rows(&[&["Doe, Jane", "She said \"hi\""], &["multi\nline", ""]])
+        );
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn keeps_rows_of_different_lengths_and_skips_empty_lines() {
+        let options = CsvOptions::new()
+            .with_delimiter(b';')
+            .with_header(CsvHeader::Absent);
+        let (_, body) = parse("a;b;c\n\n1\n2;3\n", options).expect("valid CSV");
+
+        assert_eq!(body, rows(&[&["a", "b", "c"], &["1"], &["2", "3"]]));
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn strips_the_byte_order_mark() {
+        let (header, body) =
+            parse("\u{feff}a;b\r\n1;2", CsvOptions::new().with_delimiter(b';')).expect("valid CSV");
+
+        assert_eq!(header, Some(vec!["a".to_string(), "b".to_string()]));
+        assert_eq!(body, rows(&[&["1", "2"]]));
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn unclosed_quote_extends_to_the_end() {
+        let (_, body) = parse(
+            "a,\"b\nc,d\n",
+            CsvOptions::new().with_header(CsvHeader::Absent),
+        )
+        .expect("lenient CSV");
+
+        assert_eq!(body, rows(&[&["a", "b\nc,d\n"]]));
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn reports_the_line_of_invalid_utf8() {
+        let data = b"a,b\n1,2\n3,\xFF\n";
+        let error = csv_rows(&data[..], &CsvOptions::new()).expect_err("invalid UTF-8");
+
+        let TableDataError::Csv { line, message } = error else {
+            panic!("unexpected error {error:?}");
+        };
+        assert_eq!(line, 3);
+        assert_eq!(message, "field 2 is not valid UTF-8");
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn describes_records_of_unequal_length() {
+        let error = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(&b"a,b\n1\n"[..])
+            .records()
+            .find_map(Result::err)
+            .expect("unequal lengths");
+
+        let TableDataError::Csv { line, message } = error.into() else {
+            panic!("unexpected error");
+        };
+        assert_eq!(line, 2);
+        assert_eq!(message, "the record has 1 fields instead of 2");
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn detects_the_header() {
+        let (header, body) =
+            parse("item,price\npen,1.50\nbook,12\n", CsvOptions::new()).expect("valid CSV");
+        assert_eq!(header, Some(vec!["item".to_string(), "price".to_string()]));
+        assert_eq!(body.len(), 2);
+
+        // No column only contains numbers
+        let (header, body) = parse("item,color\npen,blue\n", CsvOptions::new()).expect("valid CSV");
+        assert_eq!(header, None);
+        assert_eq!(body.len(), 2);
+
+        // The first row contains a number
+        let (header, _) = parse("2024,2025\n1,2\n", CsvOptions::new()).expect("valid CSV");
+        assert_eq!(header, None);
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn header_can_be_present_or_absent() {
+        let data = "2024,2025\n1,2\n";
+
+        let (header, body) =
+            parse(data, CsvOptions::new().with_header(CsvHeader::Present)).expect("valid CSV");
+        assert_eq!(header, Some(vec!["2024".to_string(), "2025".to_string()]));
+        assert_eq!(body, rows(&[&["1", "2"]]));
+
+        let (header, body) = parse(
+            "item,price\npen,1.50\n",
+            CsvOptions::new().with_header(CsvHeader::Absent),
+        )
+        .expect("valid CSV");
+        assert_eq!(header, None);
+        assert_eq!(body, rows(&[&["item", "price"], &["pen", "1.50"]]));
+    }
+
+    #[cfg(feature = "serde")]
+    #[derive(serde::Serialize)]
+    struct Item {
+        name: &'static str,
+        price: f64,
+        note: Option<&'static str>,
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn named_records_create_a_header() {
+        let items = [
+            Item {
+                name: "pen, blue",
+                price: 1.5,
+                note: None,
+            },
+            Item {
+                name: "book",
+                price: 12.0,
+                note: Some("signed"),
+            },
+        ];
+        let (header, body) = record_rows(items).expect("valid records");
+
+        assert_eq!(
+            header,
+            Some(vec![
+                "name".to_string(),
+                "price".to_string(),
+                "note".to_string()
+            ])
+        );
+        assert_eq!(
+            body,
+            rows(&[&["pen, blue", "1.5", ""], &["book", "12.0", "signed"]])
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn unnamed_records_have_no_header() {
+        let (header, body) = record_rows([("a", 1), ("b\nc", 2)]).expect("valid records");
+
+        assert_eq!(header, None);
+        assert_eq!(body, rows(&[&["a", "1"], &["b\nc", "2"]]));
+
+        let (header, body) = record_rows([vec![1, 2, 3], vec![4]]).expect("valid records");
+        assert_eq!(header, None);
+        assert_eq!(body, rows(&[&["1", "2", "3"], &["4"]]));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn mixed_records_are_matched_by_position() {
+        #[derive(serde::Serialize)]
+        #[serde(untagged)]
+        enum Record {
+            Named(Item),
+            Unnamed(&'static str, f64),
+        }
+
+        let (header, body) = record_rows([
+            Record::Named(Item {
+                name: "pen",
+                price: 1.5,
+                note: None,
+            }),
+            Record::Unnamed("book", 12.0),
+        ])
+        .expect("valid records");
+        assert_eq!(
+            header,
+            Some(vec![
+                "name".to_string(),
+                "price".to_string(),
+                "note".to_string()
+            ])
+        );
+        assert_eq!(body, rows(&[&["pen", "1.5", ""], &["book", "12.0"]]));
+
+        // The header is only taken from the first record
+        let (header, body) = record_rows([
+            Record::Unnamed("book", 12.0),
+            Record::Named(Item {
+                name: "pen",
+                price: 1.5,
+                note: None,
+            }),
+        ])
+        .expect("valid records");
+        assert_eq!(header, None);
+        assert_eq!(body, rows(&[&["book", "12.0"], &["pen", "1.5", ""]]));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn maps_are_not_supported() {
+        let record = std::collections::BTreeMap::from([("a", 1)]);
+
+        assert!(matches!(
+            record_rows([record]),
+            Err(TableDataError::Record(_))
+        ));
+    }
+
+    #[test]
+    fn no_rows_is_an_error() {
+        let font = Font::new(
+            printpdf::FontId::new(),
+            printpdf::Pt(10.0),
+            printpdf::Pt(0.0),
+        );
+
+        assert!(matches!(
+            table_from_rows(None, Vec::new(), font.clone()),
+            Err(TableDataError::Empty)
+        ));
+        assert!(table_from_rows(None, rows(&[&["a"]]), font).is_ok());
+    }
+}
@@ -0,0 +1,187 @@
+use printpdf::{Mm, Point, Pt, Rect};
+
+use crate::generate::{
+    bookmark::PageBookmark,
+    element::{Element, element_builder::ElementBuilder},
+    error::BuildError,
+    font::Font,
+};
+
+/// Space between the title, the leader dots and the page number
+const LEADER_GAP: Pt = Pt(4.0);
+
+/// Lists the headings of the document with their page numbers.
+///
+/// The page numbers are only known after the layout, so the document is laid out again, until
+/// the table of contents doesn't change anymore. Every entry links to its heading.
+pub struct TableOfContents {
+    font: Font,
+    max_level: u8,
+    indent: Mm,
+    leader: char,
+}
+
+impl TableOfContents {
+    pub fn new(font: Font) -> Self {
+        Self {
+            font,
+            max_level: u8::MAX,
+            indent: Mm(5.0),
+            leader: '.',
+        }
+    }
+
+    /// Headings with a higher level are not listed
+    pub fn with_max_level(mut self, max_level: u8) -> Self {
+        self.max_level = max_level;
+        self
+    }
+
+    /// Indentation per heading level
+    pub fn with_indent(mut self, indent: Mm) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    /// Character that fills the space between title and page number
+    pub fn with_leader(mut self, leader: char) -> Self {
+        self.leader = leader;
+        self
+    }
+
+    fn entries<'b>(&self, entries: &'b [PageBookmark]) -> impl Iterator<Item = &'b PageBookmark> {
+        let max_level = self.max_level;
+        entries
+            .iter()
+            .filter(move |(_, bookmark)| bookmark.level <= max_level)
+    }
+
+    fn line_height(&self) -> Pt {
+        self.font.font_size() + self.font.font_height_offset()
+    }
+
+    /// Returns the indentation, the maximum title width and the height of the entry
+    fn entry_layout(
+        &self,
+        builder: &ElementBuilder,
+        entry: &PageBookmark,
+    ) -> Result<(Pt, Pt, Pt), BuildError> {
+        let (page, bookmark) = entry;
+
+        let indent = self.indent.into_pt() * bookmark.level.saturating_sub(1) as f32;
+        let number_width = builder
+            .measure_text_manuel(&page.to_string(), &self.font, None)?
+            .0;
+        let title_width =
+            builder.remaining_width_from_cursor() - indent - number_width - LEADER_GAP * 2.0;
+        let height = builder
+            .measure_text_manuel(&bookmark.title, &self.font, Some(title_width))?
+            .1
+            .max(self.line_height());
+
+        Ok((indent, title_width, height))
+    }
+
+    fn build_entry(
+        &self,
+        builder: &mut ElementBuilder,
+        entry: &PageBookmark,
+    ) -> Result<(), BuildError> {
+        let (page, bookmark) = entry;
+        let (indent, max_title_width, height) = self.entry_layout(builder, entry)?;
+        let width = builder.remaining_width_from_cursor();
+
+        if height > builder.remaining_height_from_cursor() {
+            builder.next_page();
+        }
+
+        builder.push_text_dont_change_cursor(
+            &bookmark.title,
+            &self.font,
+            Point {
+                x: indent,
+                y: Pt(0.0),
+            },
+            Some(max_title_width),
+        )?;
+
+        let number = page.to_string();
+        let number_width = builder.measure_text_manuel(&number, &self.font, None)?.0;
+        builder.push_text_dont_change_cursor(
+            &number,
+            &self.font,
+            Point {
+                x: width - number_width,
+                y: Pt(0.0),
+            },
+            None,
+        )?;
+
+        // Leaders are only drawn for titles on a single line
+        if height <= self.line_height() {
+            let title_width = builder
+                .measure_text_manuel(&bookmark.title, &self.font, None)?
+                .0;
+            let leader_width = builder
+                .measure_text_manuel(&self.leader.to_string(), &self.font, None)?
+                .0;
+            let leader_end = width - number_width - LEADER_GAP;
+            let space = leader_end - indent - title_width - LEADER_GAP;
+
+            if leader_width > Pt(0.0) && space > leader_width {
+                let count = (space / leader_width).floor() as usize;
+                // Aligned to the page numbers, so the leaders of all entries line up
+                builder.push_text_dont_change_cursor(
+                    &self.leader.to_string().repeat(count),
+                    &self.font,
+                    Point {
+                        x: leader_end - leader_width * count as f32,
+                        y: Pt(0.0),
+                    },
+                    None,
+                )?;
+            }
+        }
+
+        let rect = Rect {
+            x: builder.cursor.x + indent,
+            y: builder.cursor.y - height,
+            width: width - indent,
+            height,
+        };
+        builder.push_page_link(rect, *page, bookmark.y);
+
+        builder.advance_cursor(height);
+        Ok(())
+    }
+}
+
+impl Element for TableOfContents {
+    fn display_name(&self) -> &str {
+        "Table of Contents"
+    }
+
+    fn calculate_width<'a>(&self, builder: &ElementBuilder<'a>) -> Result<Pt, BuildError> {
+        Ok(builder.remaining_width_from_cursor())
+    }
+
+    fn calculate_height<'a>(&self, builder: &ElementBuilder<'a>) -> Result<Pt, BuildError> {
+        self.entries(builder.document.toc_entries())
+            .try_fold(Pt(0.0), |total, entry| {
+                Ok(total + self.entry_layout(builder, entry)?.2)
+            })
+    }
+
+    fn build<'a>(&self, builder: &mut ElementBuilder<'a>) -> Result<(), BuildError> {
+        let entries = self
+            .entries(builder.use_toc_entries())
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for entry in entries.iter() {
+            self.build_entry(builder, entry)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+use std::fmt;
+
+use printpdf::{FontId, XObjectId};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum BuildErrorKind {
+    /// The font was not added to the document
+    FontNotFound(FontId),
+    /// The image was not added to the document, or the XObject has no size
+    ImageNotFound(XObjectId),
+    /// The image data could not be decoded
+    ImageDecode(String),
+    /// The layout of an element could not be computed
+    Layout(String),
+    /// A section was started inside a header, footer or another element
+    NestedSection,
+}
+
+impl fmt::Display for BuildErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildErrorKind::FontNotFound(font) => write!(f, "font {font:?} not found"),
+            BuildErrorKind::ImageNotFound(image) => write!(f, "image {image:?} not found"),
+            BuildErrorKind::ImageDecode(message) => write!(f, "failed to decode image: {message}"),
+            BuildErrorKind::Layout(message) => write!(f, "layout failed: {message}"),
+            BuildErrorKind::NestedSection => write!(
+                f,
+                "a section can only be started at the top level of the document"
+            ),
+        }
+    }
+}
+
+/// An element could not be measured or built.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BuildError {
+    pub kind: BuildErrorKind,
+    /// Display names of the elements from the document down to the element that failed
+    pub path: Vec<String>,
+}
+
+impl BuildError {
+    pub fn new(kind: BuildErrorKind) -> Self {
+        Self {
+            kind,
+            path: Vec::new(),
+        }
+    }
+
+    /// Adds the element, the error happened in, to the front of the path
+    pub fn in_element(mut self, display_name: &str) -> Self {
+        self.path.insert(0, display_name.to_string());
+        self
+    }
+}
+
+impl From<BuildErrorKind> for BuildError {
+    fn from(kind: BuildErrorKind) -> Self {
+        Self::new(kind)
+    }
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.kind)
+        } else {
+            write!(f, "{}: {}", self.path.join(" > "), self.kind)
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
@@ -1,10 +1,109 @@
+use std::sync::{Arc, PoisonError, RwLock};
+
 use printpdf::{FontId, Pt};
 
+use crate::generate::text_style::TextStyle;
+
+/// Font weight of regular text
+pub const FONT_WEIGHT_REGULAR: u16 = 400;
+/// Font weight of bold text
+pub const FONT_WEIGHT_BOLD: u16 = 700;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum FontStyle {
+    #[default]
+    Normal,
+    Italic,
+}
+
+#[derive(Clone, Debug)]
+struct FontVariant {
+    font_id: FontId,
+    weight: u16,
+    style: FontStyle,
+}
+
+/// Fonts that belong together and only differ in weight and style.
+///
+/// The variants are shared between all fonts of the family, so variants that are registered later
+/// are also found by fonts that were created before. Fonts stay `Send` and `Sync`.
+#[derive(Clone, Debug, Default)]
+pub struct FontFamily {
+    variants: Arc<RwLock<Vec<FontVariant>>>,
+}
+
+impl FontFamily {
+    pub(crate) fn add_variant(&self, font_id: FontId, weight: u16, style: FontStyle) {
+        let mut variants = self
+            .variants
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        variants.retain(|variant| variant.weight != weight || variant.style != style);
+        variants.push(FontVariant {
+            font_id,
+            weight,
+            style,
+        });
+    }
+
+    pub(crate) fn font_ids(&self) -> Vec<FontId> {
+        self.variants
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|variant| variant.font_id.clone())
+            .collect()
+    }
+
+    /// Finds the variant that matches the weight and style the closest.
+    ///
+    /// Variants with the requested style are preferred. The weight is matched like CSS does it:
+    /// For weights between 400 and 500 heavier weights up to 500 are tried first, for lighter
+    /// weights lighter variants are preferred and for heavier weights heavier variants.
+    fn resolve(&self, weight: u16, style: FontStyle) -> Option<(FontId, u16, FontStyle)> {
+        let variants = self.variants.read().unwrap_or_else(PoisonError::into_inner);
+
+        let candidates = if variants.iter().any(|variant| variant.style == style) {
+            variants
+                .iter()
+                .filter(|variant| variant.style == style)
+                .collect::<Vec<_>>()
+        } else {
+            variants.iter().collect()
+        };
+
+        candidates
+            .into_iter()
+            .min_by_key(|variant| Self::weight_distance(weight, variant.weight))
+            .map(|variant| (variant.font_id.clone(), variant.weight, variant.style))
+    }
+
+    /// Lower is better. Variants in the preferred direction always win over the others.
+    fn weight_distance(desired: u16, available: u16) -> (u8, u16) {
+        let diff = desired.abs_diff(available);
+
+        match desired {
+            400..=500 if available >= desired && available <= 500 => (0, diff),
+            400..=500 if available < desired => (1, diff),
+            400..=500 => (2, diff),
+            _ if desired < 400 && available <= desired => (0, diff),
+            _ if desired > 500 && available >= desired => (0, diff),
+            _ => (1, diff),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Font {
     font_id: FontId,
     font_size: Pt,
     font_height_offset: Pt,
+    family: Option<FontFamily>,
+    weight: u16,
+    style: FontStyle,
+    /// Used for characters that are missing in this font, in order
+    fallbacks: Vec<FontId>,
+    text_style: TextStyle,
 }
 
 impl Font {
@@ -13,9 +112,21 @@ impl Font {
             font_id,
             font_size,
             font_height_offset,
+            family: None,
+            weight: FONT_WEIGHT_REGULAR,
+            style: FontStyle::Normal,
+            fallbacks: Vec::new(),
+            text_style: TextStyle::default(),
         }
     }
 
+    pub(crate) fn with_family(mut self, family: FontFamily, weight: u16, style: FontStyle) -> Self {
+        self.family = Some(family);
+        self.weight = weight;
+        self.style = style;
+        self
+    }
+
     pub fn font_id(&self) -> FontId {
         self.font_id.clone()
     }
@@ -28,19 +139,107 @@ impl Font {
         self.font_height_offset
     }
 
+    pub fn font_weight(&self) -> u16 {
+        self.weight
+    }
+
+    pub fn font_style(&self) -> FontStyle {
+        self.style
+    }
+
     pub fn with_font_size(&self, font_size: Pt) -> Self {
         Self {
-            font_id: self.font_id.clone(),
             font_size,
-            font_height_offset: self.font_height_offset,
+            ..self.clone()
         }
     }
 
     pub fn with_font_height_offset(&self, font_height_offset: Pt) -> Self {
         Self {
-            font_id: self.font_id.clone(),
-            font_size: self.font_size,
             font_height_offset,
+            ..self.clone()
+        }
+    }
+
+    pub fn text_style(&self) -> &TextStyle {
+        &self.text_style
+    }
+
+    /// The same font, drawn with the given color, decorations and letter spacing
+    pub fn with_text_style(&self, text_style: TextStyle) -> Self {
+        Self {
+            text_style,
+            ..self.clone()
+        }
+    }
+
+    pub fn fallbacks(&self) -> &[FontId] {
+        &self.fallbacks
+    }
+
+    /// Adds a font that is used for characters, which this font and the previous fallbacks have
+    /// no glyphs for.
+    pub fn with_fallback(&self, fallback: &Font) -> Self {
+        let mut font = self.clone();
+        font.fallbacks.push(fallback.font_id());
+        font
+    }
+
+    /// The same font settings with another font file, used for runs of fallback characters
+    pub(crate) fn fallback_font(&self, font_id: FontId) -> Self {
+        Self {
+            font_id,
+            fallbacks: Vec::new(),
+            ..self.clone()
+        }
+    }
+
+    /// The variant of the family with the given weight and style.
+    ///
+    /// Returns the closest variant if there is no exact match. Fonts that were not added to a
+    /// family are returned unchanged.
+    pub fn variant(&self, weight: u16, style: FontStyle) -> Self {
+        let Some((font_id, weight, style)) = self
+            .family
+            .as_ref()
+            .and_then(|family| family.resolve(weight, style))
+        else {
+            return self.clone();
+        };
+
+        Self {
+            font_id,
+            weight,
+            style,
+            ..self.clone()
         }
     }
+
+    pub fn weight(&self, weight: u16) -> Self {
+        self.variant(weight, self.style)
+    }
+
+    pub fn bold(&self) -> Self {
+        self.weight(FONT_WEIGHT_BOLD)
+    }
+
+    pub fn regular(&self) -> Self {
+        self.variant(FONT_WEIGHT_REGULAR, FontStyle::Normal)
+    }
+
+    pub fn italic(&self) -> Self {
+        self.variant(self.weight, FontStyle::Italic)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn font_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Font>();
+        assert_send_sync::<FontFamily>();
+    }
 }
@@ -0,0 +1,183 @@
+use std::{
+    collections::BTreeMap,
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+use image::{DynamicImage, GenericImageView};
+use printpdf::{
+    DictItem, ExternalStream, ExternalXObject, Mm, Px, RawImage, RawImageData, RawImageFormat,
+};
+
+/// Options for [`Document::load_image_with`](crate::generate::document::Document::load_image_with)
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageLoadOptions {
+    max_dpi: Option<f32>,
+    rendered_width: Option<Mm>,
+    jpeg_passthrough: bool,
+}
+
+impl Default for ImageLoadOptions {
+    fn default() -> Self {
+        Self {
+            max_dpi: None,
+            rendered_width: None,
+            jpeg_passthrough: true,
+        }
+    }
+}
+
+impl ImageLoadOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Downsamples images, that would have a higher resolution when drawn with the rendered
+    /// width
+    pub fn with_max_dpi(mut self, max_dpi: f32) -> Self {
+        self.max_dpi = Some(max_dpi);
+        self
+    }
+
+    /// The width the image is drawn with, used for the maximum DPI. Defaults to the inner width of
+    /// the document, the widest an image can be drawn in the content.
+    pub fn with_rendered_width(mut self, rendered_width: Mm) -> Self {
+        self.rendered_width = Some(rendered_width);
+        self
+    }
+
+    /// Whether JPEGs are embedded as they are, without decoding and compressing them again.
+    /// Enabled by default. JPEGs, that have to be rotated or downsampled, are always decoded.
+    pub fn with_jpeg_passthrough(mut self, jpeg_passthrough: bool) -> Self {
+        self.jpeg_passthrough = jpeg_passthrough;
+        self
+    }
+
+    /// The widest the image can be in pixels, `None` if it is not downsampled
+    pub(crate) fn max_pixel_width(&self, inner_width: Mm) -> Option<u32> {
+        let max_dpi = self.max_dpi?;
+        let inches = self.rendered_width.unwrap_or(inner_width).0 / 25.4;
+        Some((inches * max_dpi).ceil().max(1.0) as u32)
+    }
+
+    pub(crate) fn jpeg_passthrough(&self) -> bool {
+        self.jpeg_passthrough
+    }
+
+    /// Identifies the image data loaded with these options
+    pub(crate) fn content_hash(&self, image_data: &[u8]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        image_data.hash(&mut hasher);
+        self.max_dpi.map(f32::to_bits).hash(&mut hasher);
+        self.rendered_width
+            .map(|width| width.0.to_bits())
+            .hash(&mut hasher);
+        self.jpeg_passthrough.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// Converts a decoded image to 8 bit RGB. The alpha channel is kept, so printpdf embeds it as
+/// a soft mask.
+pub(crate) fn raw_image(image: DynamicImage) -> RawImage {
+    let (width, height) = image.dimensions();
+    let (pixels, data_format) = if image.color().has_alpha() {
+        (image.into_rgba8().into_raw(), RawImageFormat::RGBA8)
+    } else {
+        (image.into_rgb8().into_raw(), RawImageFormat::RGB8)
+    };
+
+    RawImage {
+        pixels: RawImageData::U8(pixels),
+        width: width as usize,
+        height: height as usize,
+        data_format,
+        tag: vec![],
+    }
+}
+
+/// The size and resolution of a loaded image file, before it was downsampled
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ImageInfo {
+    pub width: Px,
+    pub height: Px,
+    /// From the metadata of the file
+    pub dpi: Option<f32>,
+}
+
+/// Size and color components of a JPEG from its frame header
+pub(crate) struct JpegInfo {
+    pub width: usize,
+    pub height: usize,
+    components: u8,
+}
+
+impl JpegInfo {
+    /// Returns `None` for data that is not a JPEG, or can't be embedded as it is
+    pub fn read(image_data: &[u8]) -> Option<Self> {
+        let mut segments = image_data.strip_prefix(&[0xFF, 0xD8])?;
+        loop {
+            let [0xFF, marker, rest @ ..] = segments else {
+                return None;
+            };
+            match *marker {
+                // Fill bytes
+                0xFF => segments = &segments[1..],
+                // Markers without a length
+                0x01 | 0xD0..=0xD7 => segments = rest,
+                // Start of the image data without a frame header, or end of the image
+                0xD9 | 0xDA => return None,
+                // Baseline, extended and progressive frames with Huffman coding. Lossless and
+                // arithmetic coded frames are not supported by all readers.
+                0xC0..=0xC2 => {
+                    let header = rest.get(2..8)?;
+                    let info = Self {
+                        height: u16::from_be_bytes([header[1], header[2]]) as usize,
+                        width: u16::from_be_bytes([header[3], header[4]]) as usize,
+                        components: header[5],
+                    };
+                    // 8 bits per component, CMYK JPEGs are often stored inverted
+                    return (header[0] == 8 && matches!(info.components, 1 | 3) && info.width > 0)
+                        .then_some(info);
+                }
+                _ => {
+                    let length = u16::from_be_bytes([*rest.first()?, *rest.get(1)?]) as usize;
+                    segments = rest.get(length..)?;
+                }
+            }
+        }
+    }
+
+    /// An image XObject with the JPEG data as it is
+    pub fn xobject(&self, image_data: &[u8]) -> ExternalXObject {
+        let color_space = match self.components {
+            1 => "DeviceGray",
+            _ => "DeviceRGB",
+        };
+        let dict = [
+            ("Type", DictItem::Name(b"XObject".to_vec())),
+            ("Subtype", DictItem::Name(b"Image".to_vec())),
+            ("Width", DictItem::Int(self.width as i64)),
+            ("Height", DictItem::Int(self.height as i64)),
+            (
+                "ColorSpace",
+                DictItem::Name(color_space.as_bytes().to_vec()),
+            ),
+            ("BitsPerComponent", DictItem::Int(8)),
+            ("Filter", DictItem::Name(b"DCTDecode".to_vec())),
+        ];
+
+        ExternalXObject {
+            stream: ExternalStream {
+                dict: dict
+                    .into_iter()
+                    .map(|(key, value)| (key.to_string(), value))
+                    .collect::<BTreeMap<_, _>>(),
+                content: image_data.to_vec(),
+                compress: false,
+            },
+            width: Some(Px(self.width)),
+            height: Some(Px(self.height)),
+            dpi: None,
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use printpdf::{Actions, BorderArray, Destination, LinkAnnotation, Op, Pt, Rect};
+
+/// Target of a clickable area
+#[derive(Clone, Debug, PartialEq)]
+pub enum Link {
+    /// Opens the URL in the viewer
+    Url(String),
+    /// Jumps to the position of an [`Anchor`](crate::generate::element::anchor::Anchor) with this
+    /// name
+    Anchor(String),
+}
+
+impl Link {
+    pub fn url(url: impl Into<String>) -> Self {
+        Link::Url(url.into())
+    }
+
+    pub fn anchor(name: impl Into<String>) -> Self {
+        Link::Anchor(name.into())
+    }
+}
+
+/// Annotation that makes `rect` open the URL
+pub(crate) fn url_annotation(rect: Rect, url: &str) -> Op {
+    link_annotation(rect, Actions::Uri(url.to_string()))
+}
+
+/// Annotation that makes `rect` jump to the position `y` on `page`, starting at 1
+pub(crate) fn goto_annotation(rect: Rect, page: usize, y: Pt) -> Op {
+    link_annotation(
+        rect,
+        Actions::Goto(Destination::Xyz {
+            page,
+            left: None,
+            top: Some(y.0),
+            zoom: None,
+        }),
+    )
+}
+
+fn link_annotation(rect: Rect, actions: Actions) -> Op {
+    Op::LinkAnnotation {
+        // Links are invisible, the content shows that it can be clicked
+        link: LinkAnnotation::new(
+            rect,
+            actions,
+            Some(BorderArray::Solid([0.0, 0.0, 0.0])),
+            None,
+            None,
+        ),
+    }
+}
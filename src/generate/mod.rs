@@ -1,7 +1,14 @@
+pub mod bookmark;
+pub mod diagnostic;
 pub mod document;
 pub mod document_builder;
 pub mod element;
+pub mod error;
 pub mod font;
+pub mod image_data;
+pub mod link;
 pub mod outline;
 pub mod padding;
+pub mod svg;
 pub mod text_gen;
+pub mod text_style;
@@ -1,5 +1,6 @@
 use printpdf::{Pt, Rgb};
 
+#[derive(Clone, Debug)]
 pub struct LineStyle {
     pub color: Rgb,
     pub thickness: Pt,
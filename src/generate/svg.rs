@@ -0,0 +1,395 @@
+use std::{collections::HashMap, sync::Arc};
+
+use lopdf::{Dictionary, Object, ObjectId, Stream, content::Content};
+use printpdf::{DictItem, ExternalStream, ExternalXObject, PdfDocument, Pt, XObjectId};
+use svg2pdf::{
+    ConversionOptions, PageOptions,
+    usvg::{self, fontdb},
+};
+
+/// SVGs are sized like raster images, which are placed at 300 DPI
+const SVG_DPI: f32 = 300.0;
+
+/// An SVG added to the document.
+///
+/// printpdf can't embed the resources of a form XObject, like gradients, masks and embedded
+/// images. The drawing is added as a placeholder without them, that is replaced with the
+/// complete drawing when the document is saved.
+pub(crate) struct SvgImage {
+    pub xobject_id: XObjectId,
+    /// A single page PDF of the drawing
+    pdf: Vec<u8>,
+    /// Whether the placeholder is missing resources of the drawing
+    pub simplified: bool,
+}
+
+impl SvgImage {
+    /// Converts the SVG and adds its placeholder to the document. Text is converted to paths with
+    /// the fonts of the database.
+    pub fn add(
+        pdf_document: &mut PdfDocument,
+        svg: &str,
+        fonts: Arc<fontdb::Database>,
+    ) -> Result<Self, String> {
+        let font_family = fonts.family_name(&fontdb::Family::SansSerif).to_string();
+        let options = usvg::Options {
+            font_family,
+            fontdb: fonts,
+            ..Default::default()
+        };
+        let tree =
+            usvg::Tree::from_str(svg, &options).map_err(|err| format!("usvg parse: {err}"))?;
+
+        let conversion = ConversionOptions {
+            embed_text: false,
+            ..Default::default()
+        };
+        let pdf = svg2pdf::to_pdf(&tree, conversion, PageOptions { dpi: SVG_DPI })
+            .map_err(|err| format!("convert svg tree to pdf: {err}"))?;
+
+        let source =
+            lopdf::Document::load_mem(&pdf).map_err(|err| format!("parse svg pdf: {err}"))?;
+        let page = SvgPage::read(&source).map_err(|err| format!("parse svg pdf: {err}"))?;
+        let resources = page
+            .resources
+            .and_then(|resources| source.dereference(resources).ok())
+            .and_then(|(_, resources)| resources.as_dict().ok());
+        let color_spaces = resources
+            .map(|resources| device_color_spaces(&source, resources))
+            .unwrap_or_default();
+        let simplified = resources.is_some_and(|resources| {
+            resources.iter().any(|(key, _)| match key.as_slice() {
+                b"ProcSet" => false,
+                b"ColorSpace" => resources
+                    .get_deref(key, &source)
+                    .and_then(Object::as_dict)
+                    .map_or(true, |spaces| spaces.len() > color_spaces.len()),
+                _ => true,
+            })
+        });
+        let content = replace_color_spaces(&page.content, &color_spaces)
+            .map_err(|err| format!("parse svg pdf: {err}"))?;
+
+        let dict = page.form_dictionary(Object::Dictionary(Dictionary::new()));
+        let placeholder = ExternalXObject {
+            stream: ExternalStream {
+                dict: dict
+                    .iter()
+                    .map(|(key, value)| {
+                        (
+                            String::from_utf8_lossy(key).into_owned(),
+                            DictItem::from_lopdf(value),
+                        )
+                    })
+                    .collect(),
+                content,
+                compress: true,
+            },
+            width: Some(Pt(page.width).into_px(SVG_DPI)),
+            height: Some(Pt(page.height).into_px(SVG_DPI)),
+            dpi: Some(SVG_DPI),
+        };
+
+        Ok(Self {
+            xobject_id: pdf_document.add_xobject(&placeholder),
+            pdf,
+            simplified,
+        })
+    }
+}
+
+/// The device color spaces, that replace the ICC color spaces of the drawing in the placeholder,
+/// by their resource names
+fn device_color_spaces(
+    source: &lopdf::Document,
+    resources: &Dictionary,
+) -> HashMap<Vec<u8>, &'static [u8]> {
+    let Ok(spaces) = resources
+        .get_deref(b"ColorSpace", source)
+        .and_then(Object::as_dict)
+    else {
+        return HashMap::new();
+    };
+
+    spaces
+        .iter()
+        .filter_map(|(name, space)| {
+            let space = source.dereference(space).ok()?.1.as_array().ok()?;
+            if space.first()?.as_name().ok()? != b"ICCBased" {
+                return None;
+            }
+            let profile = source.dereference(space.get(1)?).ok()?.1.as_stream().ok()?;
+            let device: &'static [u8] = match profile.dict.get(b"N").ok()?.as_i64().ok()? {
+                1 => b"DeviceGray",
+                3 => b"DeviceRGB",
+                4 => b"DeviceCMYK",
+                _ => return None,
+            };
+            Some((name.clone(), device))
+        })
+        .collect()
+}
+
+/// Replaces the color spaces set by the content with the device color spaces
+fn replace_color_spaces(
+    content: &[u8],
+    color_spaces: &HashMap<Vec<u8>, &'static [u8]>,
+) -> lopdf::Result<Vec<u8>> {
+    if color_spaces.is_empty() {
+        return Ok(content.to_vec());
+    }
+
+    let mut content = Content::decode(content)?;
+    for operation in &mut content.operations {
+        if !matches!(operation.operator.as_str(), "cs" | "CS") {
+            continue;
+        }
+        for operand in &mut operation.operands {
+            if let Object::Name(name) = operand
+                && let Some(device) = color_spaces.get(name)
+            {
+                *name = device.to_vec();
+            }
+        }
+    }
+
+    content.encode()
+}
+
+/// Adds a font for the text of SVGs. The first font becomes the family of text without a font
+/// family or with a generic one like "sans-serif". Returns `false`, if the data has no fonts.
+pub(crate) fn add_svg_font(database: &mut fontdb::Database, font_data: Vec<u8>) -> bool {
+    let faces = database.len();
+    database.load_font_data(font_data);
+    let first_family = database
+        .faces()
+        .next()
+        .and_then(|face| face.families.first())
+        .map(|(family, _)| family.clone());
+    if faces == 0
+        && let Some(family) = first_family
+    {
+        database.set_serif_family(&family);
+        database.set_sans_serif_family(&family);
+        database.set_monospace_family(&family);
+        database.set_cursive_family(&family);
+        database.set_fantasy_family(family);
+    }
+
+    database.len() > faces
+}
+
+/// The page of a PDF converted from an SVG
+struct SvgPage<'d> {
+    content: Vec<u8>,
+    width: f32,
+    height: f32,
+    resources: Option<&'d Object>,
+}
+
+impl<'d> SvgPage<'d> {
+    fn read(source: &'d lopdf::Document) -> lopdf::Result<Self> {
+        let page_id = *source
+            .get_pages()
+            .get(&1)
+            .ok_or(lopdf::Error::PageNumberNotFound(1))?;
+        let content = source.get_page_content(page_id)?;
+        let page = source.get_dictionary(page_id)?;
+
+        let media_box = page.get(b"MediaBox")?.as_array()?;
+        let coordinate = |index: usize| {
+            media_box
+                .get(index)
+                .ok_or(lopdf::Error::DictKey("MediaBox".to_string()))?
+                .as_float()
+        };
+
+        Ok(Self {
+            content,
+            width: coordinate(2)? - coordinate(0)?,
+            height: coordinate(3)? - coordinate(1)?,
+            resources: page.get(b"Resources").ok(),
+        })
+    }
+
+    /// A form XObject, that is scaled down to a unit square like an image
+    fn form_dictionary(&self, resources: Object) -> Dictionary {
+        Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"XObject".to_vec())),
+            ("Subtype", Object::Name(b"Form".to_vec())),
+            (
+                "BBox",
+                Object::Array(vec![
+                    0.into(),
+                    0.into(),
+                    self.width.into(),
+                    self.height.into(),
+                ]),
+            ),
+            (
+                "Matrix",
+                Object::Array(vec![
+                    (1.0 / self.width).into(),
+                    0.into(),
+                    0.into(),
+                    (1.0 / self.height).into(),
+                    0.into(),
+                    0.into(),
+                ]),
+            ),
+            ("Resources", resources),
+        ])
+    }
+}
+
+/// Replaces the placeholders of the SVGs in the saved PDF with their complete drawings
+pub(crate) fn embed_svgs(doc: &mut lopdf::Document, svgs: &[SvgImage]) -> lopdf::Result<()> {
+    let xobjects = xobject_ids(doc);
+
+    for svg in svgs {
+        // Unused SVGs are not referenced by any page
+        let Some(id) = xobjects.get(svg.xobject_id.0.as_bytes()) else {
+            continue;
+        };
+        let form = import_form(doc, &svg.pdf)?;
+        doc.objects.insert(*id, Object::Stream(form));
+    }
+
+    Ok(())
+}
+
+/// The objects of the XObjects used on the pages by their resource names
+fn xobject_ids(doc: &lopdf::Document) -> HashMap<Vec<u8>, ObjectId> {
+    fn dictionary<'d>(
+        doc: &'d lopdf::Document,
+        object: lopdf::Result<&'d Object>,
+    ) -> lopdf::Result<&'d Dictionary> {
+        object
+            .and_then(|object| doc.dereference(object))
+            .and_then(|(_, object)| object.as_dict())
+    }
+
+    let mut ids = HashMap::new();
+    for page_id in doc.get_pages().into_values() {
+        let page = dictionary(doc, doc.get_object(page_id));
+        let resources = dictionary(doc, page.and_then(|page| page.get(b"Resources")));
+        let Ok(xobjects) = dictionary(
+            doc,
+            resources.and_then(|resources| resources.get(b"XObject")),
+        ) else {
+            continue;
+        };
+
+        for (name, object) in xobjects.iter() {
+            if let Ok(id) = object.as_reference() {
+                ids.insert(name.clone(), id);
+            }
+        }
+    }
+
+    ids
+}
+
+/// Copies the page of the PDF into the document as a form XObject
+fn import_form(doc: &mut lopdf::Document, pdf: &[u8]) -> lopdf::Result<Stream> {
+    let source = lopdf::Document::load_mem(pdf)?;
+    let page = SvgPage::read(&source)?;
+
+    let resources = match page.resources {
+        Some(resources) => import_object(doc, &source, resources, &mut HashMap::new())?,
+        None => Object::Dictionary(Dictionary::new()),
+    };
+
+    let mut form = Stream::new(page.form_dictionary(resources), page.content);
+    // Uncompressed streams are written as they are
+    let _ = form.compress();
+    Ok(form)
+}
+
+/// Copies the object and all objects it references from `source` into `doc`. `ids` maps the
+/// objects of `source` to the already copied objects.
+fn import_object(
+    doc: &mut lopdf::Document,
+    source: &lopdf::Document,
+    object: &Object,
+    ids: &mut HashMap<ObjectId, ObjectId>,
+) -> lopdf::Result<Object> {
+    let imported = match object {
+        Object::Reference(id) => {
+            if let Some(imported_id) = ids.get(id) {
+                return Ok(Object::Reference(*imported_id));
+            }
+
+            let imported_id = doc.new_object_id();
+            ids.insert(*id, imported_id);
+            let imported = import_object(doc, source, source.get_object(*id)?, ids)?;
+            doc.objects.insert(imported_id, imported);
+            Object::Reference(imported_id)
+        }
+        Object::Array(items) => Object::Array(
+            items
+                .iter()
+                .map(|item| import_object(doc, source, item, ids))
+                .collect::<lopdf::Result<_>>()?,
+        ),
+        Object::Dictionary(dict) => Object::Dictionary(import_dictionary(doc, source, dict, ids)?),
+        Object::Stream(stream) => {
+            let mut stream = stream.clone();
+            stream.dict = import_dictionary(doc, source, &stream.dict, ids)?;
+            Object::Stream(stream)
+        }
+        object => object.clone(),
+    };
+
+    Ok(imported)
+}
+
+fn import_dictionary(
+    doc: &mut lopdf::Document,
+    source: &lopdf::Document,
+    dict: &Dictionary,
+    ids: &mut HashMap<ObjectId, ObjectId>,
+) -> lopdf::Result<Dictionary> {
+    let mut imported = Dictionary::new();
+    for (key, value) in dict.iter() {
+        imported.set(key.clone(), import_object(doc, source, value, ids)?);
+    }
+
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add(svg: &str) -> SvgImage {
+        let mut pdf_document = PdfDocument::new("test");
+        SvgImage::add(&mut pdf_document, svg, Arc::new(fontdb::Database::new())).unwrap()
+    }
+
+    #[test]
+    fn plain_drawing_is_not_simplified() {
+        let svg = add(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+                <rect width="10" height="10" fill="red"/>
+            </svg>"#,
+        );
+
+        assert!(!svg.simplified);
+    }
+
+    #[test]
+    fn gradient_is_simplified() {
+        let svg = add(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+                <linearGradient id="g">
+                    <stop offset="0" stop-color="red"/>
+                    <stop offset="1" stop-color="blue"/>
+                </linearGradient>
+                <rect width="10" height="10" fill="url(#g)"/>
+            </svg>"#,
+        );
+
+        assert!(svg.simplified);
+    }
+}
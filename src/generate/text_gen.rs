@@ -1,5 +1,39 @@
+use std::borrow::Cow;
+
 use printpdf::{FontId, PdfDocument, Pt, ShapedText, TextShapingOptions};
 
+use crate::generate::document::PageInfo;
+use crate::generate::error::{BuildError, BuildErrorKind};
+
+/// Replaced with the current page number, when used in a header or footer.
+pub const PAGE_NUMBER_PLACEHOLDER: &str = "{page}";
+/// Replaced with the total number of pages, when used in a header or footer.
+pub const PAGE_COUNT_PLACEHOLDER: &str = "{pages}";
+
+/// Horizontal alignment of the lines of a text
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+    /// Stretches the space between words, so that every line except the last one fills the
+    /// whole width
+    Justify,
+}
+
+/// Replaces the page placeholders in the text with the values of the given page.
+pub fn resolve_page_placeholders(text: &str, page_info: PageInfo) -> Cow<'_, str> {
+    if !text.contains(PAGE_NUMBER_PLACEHOLDER) && !text.contains(PAGE_COUNT_PLACEHOLDER) {
+        return Cow::Borrowed(text);
+    }
+
+    Cow::Owned(
+        text.replace(PAGE_NUMBER_PLACEHOLDER, &page_info.page.to_string())
+            .replace(PAGE_COUNT_PLACEHOLDER, &page_info.total.to_string()),
+    )
+}
+
 fn space_between_newlines(input: &str) -> String {
     let mut result = String::with_capacity(input.len());
     let mut prev_was_nl = false;
@@ -22,9 +56,30 @@ pub fn shape_text(
     font_height_offset: Pt,
     text: &str,
     max_width: Option<Pt>,
-) -> ShapedText {
+) -> Result<ShapedText, BuildError> {
+    shape_text_with_letter_spacing(
+        doc,
+        font,
+        font_size,
+        font_height_offset,
+        Pt(0.0),
+        text,
+        max_width,
+    )
+}
+
+/// Like [`shape_text`], with `letter_spacing` added between the characters of every word
+pub fn shape_text_with_letter_spacing(
+    doc: &PdfDocument,
+    font: FontId,
+    font_size: Pt,
+    font_height_offset: Pt,
+    letter_spacing: Pt,
+    text: &str,
+    max_width: Option<Pt>,
+) -> Result<ShapedText, BuildError> {
     if !doc.resources.fonts.map.contains_key(&font) {
-        panic!("Font resource not found for font ID: {:?}", font);
+        return Err(BuildErrorKind::FontNotFound(font).into());
     }
 
     // We need to add a space between two new lines to avoid not showing the second new line.
@@ -34,15 +89,12 @@ pub fn shape_text(
         font_size,
         //line_height: Some(Pt(font_size.0 + font_height_offset)),
         line_height: Some(font_size + font_height_offset),
-        max_width: max_width,
+        max_width,
+        letter_spacing: (letter_spacing != Pt(0.0)).then_some(letter_spacing.0),
         ..Default::default()
     };
 
-    let parsed_font = doc.resources.fonts.map.get(&font).unwrap();
-
-    let space_width = parsed_font.get_space_width().unwrap_or_default() as f32
-        / parsed_font.font_metrics.units_per_em as f32
-        * font_size.0;
+    let space_width = space_width(doc, &font, font_size).0;
 
     let spaces_at_end = formated_text
         .chars()
@@ -52,13 +104,125 @@ pub fn shape_text(
 
     let mut shaped_text = doc
         .shape_text(&formated_text, &font, &shaping_options)
-        .unwrap();
+        .ok_or_else(|| BuildErrorKind::FontNotFound(font.clone()))?;
 
     shaped_text.height = shaped_text.lines.len() as f32 * (font_size + font_height_offset).0;
     // Dividing by two makes it better. Cant figure out how to calculate it properly.
     shaped_text.width += (space_width * spaces_at_end) / 2.0;
 
-    shaped_text
+    Ok(shaped_text)
+}
+
+/// Whether the font has a glyph for the character
+pub fn has_glyph(doc: &PdfDocument, font: &FontId, c: char) -> bool {
+    doc.resources
+        .fonts
+        .map
+        .get(font)
+        .and_then(|parsed_font| parsed_font.lookup_glyph_index(c as u32))
+        .is_some_and(|glyph| glyph != 0)
+}
+
+/// Splits the text into runs, that can each be shaped with a single font.
+///
+/// Every character uses the first font in `fonts` that has a glyph for it. Whitespace continues
+/// the current run and characters that no font supports use the first font. Returns the text of
+/// each run with the index of its font.
+pub fn split_into_font_runs(
+    doc: &PdfDocument,
+    text: &str,
+    fonts: &[FontId],
+) -> Vec<(String, usize)> {
+    let mut runs: Vec<(String, usize)> = Vec::new();
+
+    for c in text.chars() {
+        let font_index = if c.is_whitespace() {
+            runs.last().map_or(0, |(_, index)| *index)
+        } else {
+            fonts
+                .iter()
+                .position(|font| has_glyph(doc, font, c))
+                .unwrap_or(0)
+        };
+
+        match runs.last_mut() {
+            Some((run, index)) if *index == font_index => run.push(c),
+            _ => runs.push((c.to_string(), font_index)),
+        }
+    }
+
+    runs
+}
+
+/// Width of a single space in the given font
+pub fn space_width(doc: &PdfDocument, font: &FontId, font_size: Pt) -> Pt {
+    let Some(parsed_font) = doc.resources.fonts.map.get(font) else {
+        return Pt(0.0);
+    };
+
+    Pt(parsed_font.get_space_width().unwrap_or_default() as f32
+        / parsed_font.font_metrics.units_per_em as f32
+        * font_size.0)
+}
+
+/// Positions every line of the shaped text within the given width.
+///
+/// Justified lines that end with a line break are not stretched, which is detected by checking
+/// if the first word of the next line would have fit.
+pub fn align_shaped_text(text: &mut ShapedText, width: Pt, align: TextAlign, space_width: Pt) {
+    if align == TextAlign::Left {
+        return;
+    }
+
+    let is_word = |word: &&printpdf::ShapedWord| !word.text.trim().is_empty();
+
+    for index in 0..text.lines.len() {
+        let next_word_width = text
+            .lines
+            .get(index + 1)
+            .and_then(|line| line.words.iter().find(is_word))
+            .map(|word| word.width);
+
+        let line = &mut text.lines[index];
+        let Some(line_end) = line
+            .words
+            .iter()
+            .filter(is_word)
+            .map(|word| word.x + word.width)
+            .reduce(f32::max)
+        else {
+            continue;
+        };
+
+        let free = width.0 - line_end;
+        if free <= 0.0 {
+            continue;
+        }
+
+        match align {
+            TextAlign::Left => {}
+            TextAlign::Center => line.x += free / 2.0,
+            TextAlign::Right => line.x += free,
+            TextAlign::Justify => {
+                let is_line_break =
+                    next_word_width.is_none_or(|next| line_end + space_width.0 + next <= width.0);
+                let word_count = line.words.iter().filter(is_word).count();
+                if is_line_break || word_count < 2 {
+                    continue;
+                }
+
+                let gap = free / (word_count - 1) as f32;
+                for (index, word) in line
+                    .words
+                    .iter_mut()
+                    .filter(|word| !word.text.trim().is_empty())
+                    .enumerate()
+                {
+                    word.x += gap * index as f32;
+                }
+            }
+        }
+    }
 }
 
 /// This will cut the shaped text to the given max height.
@@ -0,0 +1,160 @@
+use printpdf::{Color, Op, PaintMode, Point, Polygon, Pt, Rect};
+
+/// Thickness of underline and strikethrough relative to the font size
+const DECORATION_THICKNESS: f32 = 0.05;
+/// Distance of the underline below the baseline relative to the font size
+const UNDERLINE_OFFSET: f32 = 0.12;
+/// Height of the strikethrough above the baseline relative to the font size
+const STRIKETHROUGH_OFFSET: f32 = 0.3;
+/// How far the highlight reaches below the baseline relative to the font size
+const HIGHLIGHT_DESCENT: f32 = 0.25;
+
+/// Visual styling of text, that does not change the font itself.
+///
+/// The style is attached to a [`Font`](crate::generate::font::Font), so it can be used for
+/// paragraphs, single parts of rich text and table cells.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TextStyle {
+    pub color: Option<Color>,
+    pub underline: bool,
+    pub strikethrough: bool,
+    /// Background color behind the text
+    pub highlight: Option<Color>,
+    /// Additional space between two characters
+    pub letter_spacing: Pt,
+}
+
+impl TextStyle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    pub fn with_underline(mut self) -> Self {
+        self.underline = true;
+        self
+    }
+
+    pub fn with_strikethrough(mut self) -> Self {
+        self.strikethrough = true;
+        self
+    }
+
+    pub fn with_highlight(mut self, color: Color) -> Self {
+        self.highlight = Some(color);
+        self
+    }
+
+    pub fn with_letter_spacing(mut self, letter_spacing: Pt) -> Self {
+        self.letter_spacing = letter_spacing;
+        self
+    }
+
+    /// Ops that are drawn before the text, like the highlight.
+    ///
+    /// `origin` is the start of the baseline, `width` the width of the visible text.
+    pub(crate) fn background_ops(
+        &self,
+        origin: Point,
+        width: Pt,
+        font_size: Pt,
+        line_height: Pt,
+    ) -> Vec<Op> {
+        let Some(highlight) = &self.highlight else {
+            return Vec::new();
+        };
+
+        let bottom = origin.y - font_size * HIGHLIGHT_DESCENT;
+        Self::fill_rect_ops(
+            Point {
+                x: origin.x,
+                y: bottom + line_height,
+            },
+            width,
+            line_height,
+            highlight.clone(),
+        )
+    }
+
+    /// Ops that are drawn after the text, like underline and strikethrough.
+    pub(crate) fn foreground_ops(&self, origin: Point, width: Pt, font_size: Pt) -> Vec<Op> {
+        let color = self.text_color();
+        let thickness = font_size * DECORATION_THICKNESS;
+
+        let mut ops = Vec::new();
+        if self.underline {
+            ops.extend(Self::fill_rect_ops(
+                Point {
+                    x: origin.x,
+                    y: origin.y - font_size * UNDERLINE_OFFSET,
+                },
+                width,
+                thickness,
+                color.clone(),
+            ));
+        }
+        if self.strikethrough {
+            ops.extend(Self::fill_rect_ops(
+                Point {
+                    x: origin.x,
+                    y: origin.y + font_size * STRIKETHROUGH_OFFSET + thickness / 2.0,
+                },
+                width,
+                thickness,
+                color,
+            ));
+        }
+
+        ops
+    }
+
+    /// Ops that have to be inside the graphics state of the text
+    pub(crate) fn text_state_ops(&self) -> Vec<Op> {
+        let mut ops = Vec::new();
+        if let Some(color) = &self.color {
+            ops.push(Op::SetFillColor { col: color.clone() });
+        }
+        if self.letter_spacing != Pt(0.0) {
+            ops.push(Op::SetCharacterSpacing {
+                multiplier: self.letter_spacing.0,
+            });
+        }
+        ops
+    }
+
+    fn text_color(&self) -> Color {
+        self.color
+            .clone()
+            .unwrap_or(Color::Rgb(printpdf::Rgb::new(0.0, 0.0, 0.0, None)))
+    }
+
+    /// `top_left` is the top left corner of the rect
+    fn fill_rect_ops(top_left: Point, width: Pt, height: Pt, color: Color) -> Vec<Op> {
+        if width <= Pt(0.0) {
+            return Vec::new();
+        }
+
+        let rect = Rect {
+            x: top_left.x,
+            y: top_left.y,
+            width,
+            height,
+        };
+
+        vec![
+            Op::SaveGraphicsState,
+            Op::SetFillColor { col: color },
+            Op::DrawPolygon {
+                polygon: Polygon {
+                    mode: PaintMode::Fill,
+                    ..rect.to_polygon()
+                },
+            },
+            Op::RestoreGraphicsState,
+        ]
+    }
+}